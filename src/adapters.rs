@@ -0,0 +1,210 @@
+//! Heru Handika
+//! Adapter content. Each read is searched for the start
+//! of known adapters. Reads count toward every position
+//! from the first match to the end of the read.
+
+use std::io::BufRead;
+use std::path::Path;
+
+use crate::error::QcError;
+use crate::reader;
+
+// Adapter bases searched in the reads.
+const PROBE_LEN: usize = 12;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Adapter {
+    pub name: String,
+    pub seq: Vec<u8>,
+}
+
+impl Adapter {
+    fn new(name: &str, seq: &[u8]) -> Self {
+        Self {
+            name: name.to_string(),
+            seq: seq.to_ascii_uppercase(),
+        }
+    }
+
+    /// Position of the first adapter match in the read.
+    pub fn find(&self, read: &[u8]) -> Option<usize> {
+        let probe = &self.seq[..self.seq.len().min(PROBE_LEN)];
+        read.windows(probe.len())
+            .position(|window| window.eq_ignore_ascii_case(probe))
+    }
+}
+
+pub fn default_adapters() -> Vec<Adapter> {
+    vec![
+        Adapter::new("Illumina TruSeq", b"AGATCGGAAGAGC"),
+        Adapter::new("Illumina Nextera", b"CTGTCTCTTATACACATCT"),
+        Adapter::new("Illumina Small RNA 3'", b"TGGAATTCTCGGGTGCCAAGG"),
+        Adapter::new("Poly-A", b"AAAAAAAAAAAAAAAAAAAA"),
+    ]
+}
+
+/// Reads custom adapters from a FASTA file.
+/// The name is the first word of the header.
+pub fn read_adapters(input: &Path) -> Result<Vec<Adapter>, QcError> {
+    let buff = reader::open_input(input)?;
+    let mut adapters: Vec<Adapter> = Vec::new();
+
+    for line in buff.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if let Some(header) = line.strip_prefix('>') {
+            let name = header.split_whitespace().next().unwrap_or_default();
+            adapters.push(Adapter::new(name, b""));
+        } else if let Some(adapter) = adapters.last_mut() {
+            adapter.seq.extend(line.to_ascii_uppercase().bytes());
+        }
+    }
+
+    adapters.retain(|adapter| !adapter.seq.is_empty());
+
+    if adapters.is_empty() {
+        return Err(QcError::EmptyInput);
+    }
+
+    Ok(adapters)
+}
+
+/// Number of reads with each adapter starting at each position.
+#[derive(Default)]
+pub struct AdapterContent {
+    starts: Vec<Vec<u64>>,
+    reads: u64,
+}
+
+impl AdapterContent {
+    /// Adds the first match of each adapter in a read.
+    pub fn add(&mut self, hits: &[Option<usize>]) {
+        if self.starts.len() < hits.len() {
+            self.starts.resize(hits.len(), Vec::new());
+        }
+
+        hits.iter()
+            .zip(self.starts.iter_mut())
+            .for_each(|(hit, starts)| {
+                if let Some(pos) = hit {
+                    if starts.len() <= *pos {
+                        starts.resize(pos + 1, 0);
+                    }
+                    starts[*pos] += 1;
+                }
+            });
+        self.reads += 1;
+    }
+
+    pub fn merge(&mut self, other: &AdapterContent) {
+        if self.starts.len() < other.starts.len() {
+            self.starts.resize(other.starts.len(), Vec::new());
+        }
+
+        self.starts.iter_mut()
+            .zip(other.starts.iter())
+            .for_each(|(starts, other)| {
+                if starts.len() < other.len() {
+                    starts.resize(other.len(), 0);
+                }
+                starts.iter_mut()
+                    .zip(other.iter())
+                    .for_each(|(count, other)| *count += other);
+            });
+        self.reads += other.reads;
+    }
+
+    /// Cumulative percentage of reads with each adapter, up to
+    /// the last adapter start. The content stays the same after it.
+    pub fn summarize(&self, adapters: &[Adapter]) -> Vec<AdapterProfile> {
+        let read_len = self.starts.iter().map(Vec::len).max().unwrap_or(0);
+
+        adapters.iter()
+            .enumerate()
+            .map(|(idx, adapter)| {
+                let starts = self.starts.get(idx).map(Vec::as_slice).unwrap_or_default();
+                let mut cumsum = 0;
+                let content = (0..read_len)
+                    .map(|pos| {
+                        cumsum += starts.get(pos).copied().unwrap_or(0);
+                        cumsum as f64 / self.reads as f64 * 100.0
+                    })
+                    .collect();
+
+                AdapterProfile { name: adapter.name.clone(), content }
+            })
+            .collect()
+    }
+}
+
+pub struct AdapterProfile {
+    pub name: String,
+    /// Cumulative percentage of reads at each position.
+    pub content: Vec<f64>,
+}
+
+impl AdapterProfile {
+    /// Percentage of reads with the adapter anywhere.
+    pub fn total(&self) -> f64 {
+        self.content.last().copied().unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn adapter_content_test() {
+        let adapters = default_adapters();
+        let reads: [&[u8]; 4] = [
+            b"ACGTAGATCGGAAGAGCACAC",
+            b"agatcggaagagcTTTTTTTT",
+            b"ACGTACGTACGT",
+            b"CTGTCTCTTATA",
+        ];
+
+        let mut content = AdapterContent::default();
+        let mut other = AdapterContent::default();
+        reads.iter()
+            .enumerate()
+            .for_each(|(idx, read)| {
+                let hits: Vec<Option<usize>> = adapters.iter().map(|a| a.find(read)).collect();
+                if idx < 2 {
+                    content.add(&hits);
+                } else {
+                    other.add(&hits);
+                }
+            });
+        content.merge(&other);
+
+        let res = content.summarize(&adapters);
+
+        assert_eq!(4, res.len());
+        assert_eq!("Illumina TruSeq", res[0].name);
+        assert_eq!(5, res[0].content.len());
+        assert_eq!(5, res[3].content.len());
+        assert_eq!(25.0, res[0].content[0]);
+        assert_eq!(25.0, res[0].content[3]);
+        assert_eq!(50.0, res[0].content[4]);
+        assert_eq!(50.0, res[0].total());
+        assert_eq!(25.0, res[1].content[0]);
+        assert_eq!(0.0, res[3].total());
+    }
+
+    #[test]
+    fn read_adapters_test() {
+        let input = std::env::temp_dir().join("sqc_read_adapters_test.fasta");
+        fs::write(&input, ">custom_1 my adapter\nACGTACGT\nacgt\n>empty\n>custom_2\nTTTT\n").unwrap();
+
+        let adapters = read_adapters(&input).unwrap();
+        fs::remove_file(&input).unwrap();
+
+        assert_eq!(2, adapters.len());
+        assert_eq!(Adapter::new("custom_1", b"ACGTACGTACGT"), adapters[0]);
+        assert_eq!("custom_2", adapters[1].name);
+        assert_eq!(Some(2), adapters[1].find(b"GGTTTT"));
+    }
+}
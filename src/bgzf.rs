@@ -0,0 +1,198 @@
+//! Heru Handika
+//! Module to decompress BGZF files in parallel.
+//! BGZF files are a series of small gzip blocks,
+//! each can be inflated independently.
+
+use std::io::{self, BufRead, Read};
+
+use flate2::read::GzDecoder;
+use rayon::prelude::*;
+
+// Gzip header up to the extra field length.
+const HEADER_LEN: usize = 12;
+// Blocks inflated per thread in each batch.
+const BLOCKS_PER_THREAD: usize = 8;
+
+/// Reader that inflates batches of BGZF blocks in parallel
+/// and returns the data in the original order.
+pub struct BgzfReader<R> {
+    reader: R,
+    buff: Vec<u8>,
+    pos: usize,
+    batch: usize,
+    eof: bool,
+}
+
+impl<R: Read> BgzfReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_batch(reader, rayon::current_num_threads() * BLOCKS_PER_THREAD)
+    }
+
+    fn with_batch(reader: R, batch: usize) -> Self {
+        Self {
+            reader,
+            buff: Vec::new(),
+            pos: 0,
+            batch,
+            eof: false,
+        }
+    }
+
+    fn fill_batch(&mut self) -> io::Result<()> {
+        let mut blocks = Vec::with_capacity(self.batch);
+
+        while blocks.len() < self.batch {
+            match self.read_block()? {
+                Some(block) => blocks.push(block),
+                None => {
+                    self.eof = true;
+                    break;
+                }
+            }
+        }
+
+        let data = blocks.par_iter()
+            .map(|block| inflate_block(block))
+            .collect::<io::Result<Vec<Vec<u8>>>>()?;
+
+        self.buff = data.concat();
+        self.pos = 0;
+
+        Ok(())
+    }
+
+    // Reads a compressed block. Returns None at the end of the file.
+    fn read_block(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut block = vec![0; HEADER_LEN];
+        let header_len = read_full(&mut self.reader, &mut block)?;
+
+        if header_len == 0 {
+            return Ok(None);
+        } else if header_len < HEADER_LEN || block[3] & 0x04 == 0 {
+            return Err(invalid_block("TRUNCATED OR NON-BGZF BLOCK"));
+        }
+
+        let xlen = u16::from_le_bytes([block[10], block[11]]) as usize;
+        block.resize(HEADER_LEN + xlen, 0);
+        self.reader.read_exact(&mut block[HEADER_LEN..])?;
+
+        let block_size = block_size(&block[HEADER_LEN..])
+            .ok_or_else(|| invalid_block("MISSING BGZF BLOCK SIZE"))?;
+
+        if block_size < block.len() {
+            return Err(invalid_block("INVALID BGZF BLOCK SIZE"));
+        }
+
+        let start = block.len();
+        block.resize(block_size, 0);
+        self.reader.read_exact(&mut block[start..])?;
+
+        Ok(Some(block))
+    }
+}
+
+impl<R: Read> Read for BgzfReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let data = self.fill_buf()?;
+        let len = data.len().min(out.len());
+        out[..len].copy_from_slice(&data[..len]);
+        self.consume(len);
+
+        Ok(len)
+    }
+}
+
+impl<R: Read> BufRead for BgzfReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // Empty blocks, such as the EOF marker, inflate to nothing.
+        while self.pos == self.buff.len() && !self.eof {
+            self.fill_batch()?;
+        }
+
+        Ok(&self.buff[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buff.len());
+    }
+}
+
+// Total block size from the 'BC' extra subfield.
+fn block_size(extra: &[u8]) -> Option<usize> {
+    let mut fields = extra;
+
+    while fields.len() >= 4 {
+        let len = u16::from_le_bytes([fields[2], fields[3]]) as usize;
+        let data = fields.get(4..4 + len)?;
+
+        if fields[..2] == [b'B', b'C'] && len == 2 {
+            return Some(u16::from_le_bytes([data[0], data[1]]) as usize + 1);
+        }
+
+        fields = &fields[4 + len..];
+    }
+
+    None
+}
+
+fn inflate_block(block: &[u8]) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    GzDecoder::new(block).read_to_end(&mut data)?;
+
+    Ok(data)
+}
+
+// Like read_exact, but returns the bytes read at the end of the file.
+fn read_full<R: Read>(reader: &mut R, buff: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+
+    while len < buff.len() {
+        match reader.read(&mut buff[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(len)
+}
+
+fn invalid_block(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use flate2::read::MultiGzDecoder;
+
+    #[test]
+    fn bgzf_reader_test() {
+        let input = "test_files/valid_bgzf.fastq.gz";
+        let mut expected = Vec::new();
+        MultiGzDecoder::new(File::open(input).unwrap())
+            .read_to_end(&mut expected)
+            .unwrap();
+
+        [1, 2, 64].iter()
+            .for_each(|batch| {
+                let mut data = Vec::new();
+                BgzfReader::with_batch(File::open(input).unwrap(), *batch)
+                    .read_to_end(&mut data)
+                    .unwrap();
+
+                assert_eq!(expected, data);
+            });
+    }
+
+    #[test]
+    fn non_bgzf_error_test() {
+        let input = File::open("test_files/valid.fastq.gz").unwrap();
+        let mut data = Vec::new();
+        let res = BgzfReader::new(input).read_to_end(&mut data);
+
+        assert!(res.is_err());
+    }
+}
@@ -2,13 +2,18 @@
 //! 
 //! Command line parser
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use clap::{App, AppSettings, Arg};
+use clap::{App, AppSettings, Arg, ArgMatches};
 
+use crate::adapters::{self, Adapter};
+use crate::error::QcError;
 use crate::input;
+use crate::kmers;
+use crate::params::QcParams;
+use crate::stats::LengthBins;
 
-pub fn get_cli(version: &str) {
+pub fn get_cli(version: &str) -> Result<(), QcError> {
     let args = App::new("simpleQC")
                 .version(version)
                 .about("A simple CLI app for NGS quality control.")
@@ -31,7 +36,7 @@ pub fn get_cli(version: &str) {
                             Arg::with_name("file")
                                 .short("f")
                                 .long("file")
-                                .help("Inputs FASTQ files. Allows multiple inputs")
+                                .help("Inputs FASTQ files. Allows multiple inputs. Use '-' for stdin")
                                 .conflicts_with_all(&[ "dir", "wildcard", "wdir"])
                                 .multiple(true)
                                 .value_name("FASTQ FILE")
@@ -57,6 +62,15 @@ pub fn get_cli(version: &str) {
                                 .value_name("PARENT DIR")
                             )
                         
+                        .arg(
+                            Arg::with_name("name")
+                                .long("name")
+                                .help("Sample name for stdin inputs")
+                                .requires("file")
+                                .takes_value(true)
+                                .value_name("NAME")
+                            )
+
                         .arg(
                             Arg::with_name("nocsv")
                                 .long("nocsv")
@@ -64,10 +78,90 @@ pub fn get_cli(version: &str) {
                                 .takes_value(false)
                             )
                         
+                        .arg(
+                            Arg::with_name("qenc")
+                                .long("qenc")
+                                .help("Sets the q-score encoding. Detected from the reads by default")
+                                .possible_values(&["phred33", "phred64", "solexa"])
+                                .takes_value(true)
+                                .value_name("ENCODING")
+                            )
+
+                        .arg(
+                            Arg::with_name("lowq")
+                                .long("low-q")
+                                .help("Sets the q-score below which bases are low quality [default: 20]")
+                                .validator(is_qscore)
+                                .takes_value(true)
+                                .value_name("SCORE")
+                            )
+
+                        .arg(
+                            Arg::with_name("dupsample")
+                                .long("dup-sample")
+                                .help("Estimates duplication from the first reads of each file [default: 1000000]")
+                                .validator(is_positive)
+                                .takes_value(true)
+                                .value_name("READS")
+                            )
+
+                        .arg(
+                            Arg::with_name("overrep")
+                                .long("overrep")
+                                .help("Reports sequences above this percentage of reads [default: 0.1]")
+                                .validator(is_percent)
+                                .takes_value(true)
+                                .value_name("PERCENT")
+                            )
+
+                        .arg(
+                            Arg::with_name("adapters")
+                                .long("adapters")
+                                .help("Adds custom adapters from a FASTA file")
+                                .takes_value(true)
+                                .value_name("FASTA")
+                            )
+
+                        .arg(
+                            Arg::with_name("kmer")
+                                .long("kmer")
+                                .help("Sets the k-mer size of the k-mer content [default: 7]")
+                                .validator(is_kmer_size)
+                                .takes_value(true)
+                                .value_name("K")
+                            )
+
+                        .arg(
+                            Arg::with_name("mintail")
+                                .long("min-tail")
+                                .help("Sets the shortest poly-G, poly-A, or poly-T tail [default: 10]")
+                                .validator(is_positive)
+                                .takes_value(true)
+                                .value_name("LENGTH")
+                            )
+
+                        .arg(
+                            Arg::with_name("binwidth")
+                                .long("bin-width")
+                                .help("Sets the bin width of the read length histogram [default: 10]")
+                                .conflicts_with("logbins")
+                                .validator(is_positive)
+                                .takes_value(true)
+                                .value_name("LENGTH")
+                            )
+
+                        .arg(
+                            Arg::with_name("logbins")
+                                .long("log-bins")
+                                .help("Uses bins doubling in width for the read length histogram")
+                                .takes_value(false)
+                            )
+
                         .arg(
                             Arg::with_name("nogz")
                                 .long("nogz")
-                                .help("Input is an uncompressed FASTQ files")
+                                .help("Deprecated. Compression is detected automatically")
+                                .hidden(true)
                                 .conflicts_with_all(&["file", "wdir", "wildcard"])
                                 .takes_value(false)
                             )
@@ -89,7 +183,7 @@ pub fn get_cli(version: &str) {
                             Arg::with_name("file")
                                 .short("f")
                                 .long("file")
-                                .help("Inputs FASTA files. Allows multiple inputs")
+                                .help("Inputs FASTA files. Allows multiple inputs. Use '-' for stdin")
                                 .conflicts_with_all(&[ "dir", "wildcard", "wdir"])
                                 .multiple(true)
                                 .value_name("FASTA FILES")
@@ -115,6 +209,15 @@ pub fn get_cli(version: &str) {
                                 .value_name("PARENT DIR")
                             )
                         
+                        .arg(
+                            Arg::with_name("name")
+                                .long("name")
+                                .help("Sample name for stdin inputs")
+                                .requires("file")
+                                .takes_value(true)
+                                .value_name("NAME")
+                            )
+
                         .arg(
                             Arg::with_name("nocsv")
                                 .long("nocsv")
@@ -125,7 +228,8 @@ pub fn get_cli(version: &str) {
                         .arg(
                             Arg::with_name("gz")
                                 .long("gz")
-                                .help("Input is a compressed FASTA file")
+                                .help("Deprecated. Compression is detected automatically")
+                                .hidden(true)
                                 .conflicts_with_all(&["file", "wdir", "wildcard"])
                                 .takes_value(false)
                             )
@@ -136,101 +240,157 @@ pub fn get_cli(version: &str) {
     match args.subcommand() {
 
         ("fastq", Some(fastq_matches)) => {
-            let mut iscsv = true;
-            if fastq_matches.is_present("nocsv") {
-                iscsv = false;
-            }
+            let params = get_params(fastq_matches)?;
 
             if fastq_matches.is_present("dir") {
                 let entry: &str = fastq_matches.value_of("dir").unwrap();
-                let mut ext = String::new();
-
-                if fastq_matches.is_present("nogz") {
-                    ext.push_str("fastq");
-                } else {
-                    ext.push_str("fastq.gz");
-                }
-
-                process_dir(entry, &ext, iscsv, true);
+                process_dir(entry, &params, true)
 
             } else if fastq_matches.is_present("file") {
                 let entries: Vec<&str> = fastq_matches
                     .values_of("file").unwrap().collect();
-                process_multiple_files(&entries, iscsv, true);
+                process_multiple_files(&entries, &params, true)
 
             } else if fastq_matches.is_present("wildcard") {
                 let entries: Vec<&str> = fastq_matches
                     .values_of("wildcard").unwrap().collect();
 
-                process_multiple_files(&entries, iscsv, true);
+                process_multiple_files(&entries, &params, true)
                 
             } else if fastq_matches.is_present("wdir") {
                 let entry = fastq_matches.value_of("wdir").unwrap();
-                input::traverse_dir(&entry, iscsv, true); // true for fastq
+                input::traverse_dir(entry, &params, true) // true for fastq
                 
             } else {
                 println!("No command provided!");
+                Ok(())
             }
         }
         
         ("fasta", Some(fasta_matches)) => {
-            let mut iscsv = true;
-            if fasta_matches.is_present("nocsv") {
-                iscsv = false;
-            }
+            let params = get_params(fasta_matches)?;
 
             if fasta_matches.is_present("dir") {
                 let entry: &str = fasta_matches.value_of("dir").unwrap();
-                let mut ext = String::new();
-
-                if fasta_matches.is_present("gz") {
-                    ext.push_str("fasta.gz");
-                } else {
-                    ext.push_str("fasta");
-                }
-
-                process_dir(entry, &ext, iscsv, false);
+                process_dir(entry, &params, false)
 
             } else if fasta_matches.is_present("file") {
                 let entries: Vec<&str> = fasta_matches
                     .values_of("file").unwrap().collect();
-                process_multiple_files(&entries, iscsv, false); // false for fasta
+                process_multiple_files(&entries, &params, false) // false for fasta
 
             } else if fasta_matches.is_present("wildcard") {
                 let entries: Vec<&str> = fasta_matches
                     .values_of("wildcard").unwrap().collect();
 
-                process_multiple_files(&entries, iscsv, false);
+                process_multiple_files(&entries, &params, false)
                 
             } else if fasta_matches.is_present("wdir") {
                 let entry: &str = fasta_matches.value_of("wdir").unwrap();
-                input::traverse_dir(&entry, iscsv, false); // false for fasta
+                input::traverse_dir(entry, &params, false) // false for fasta
 
             } else {
                 println!("No command provided!");
+                Ok(())
             }
         }
 
 
         _ => unreachable!("Unreachable commands!"),
-    };
+    }
+}
+ 
+fn get_params(matches: &ArgMatches) -> Result<QcParams, QcError> {
+    let defaults = QcParams::default();
+
+    Ok(QcParams {
+        iscsv: !matches.is_present("nocsv"),
+        name: matches.value_of("name").map(String::from),
+        encoding: matches.value_of("qenc").and_then(|enc| enc.parse().ok()),
+        length_bins: get_length_bins(matches),
+        low_q: matches.value_of("lowq")
+            .and_then(|score| score.parse().ok())
+            .unwrap_or(defaults.low_q),
+        dup_sample: matches.value_of("dupsample")
+            .and_then(|reads| reads.parse().ok())
+            .unwrap_or(defaults.dup_sample),
+        overrep_min: matches.value_of("overrep")
+            .and_then(|percent| percent.parse().ok())
+            .unwrap_or(defaults.overrep_min),
+        adapters: get_adapters(matches, defaults.adapters)?,
+        kmer_size: matches.value_of("kmer")
+            .and_then(|k| k.parse().ok())
+            .unwrap_or(defaults.kmer_size),
+        min_tail: matches.value_of("mintail")
+            .and_then(|len| len.parse().ok())
+            .unwrap_or(defaults.min_tail),
+    })
+}
+
+// Adds the custom adapters to the built-in ones.
+fn get_adapters(matches: &ArgMatches, mut adapters: Vec<Adapter>) 
+    -> Result<Vec<Adapter>, QcError> {
+    if let Some(input) = matches.value_of("adapters") {
+        adapters.extend(adapters::read_adapters(Path::new(input))?);
+    }
+
+    Ok(adapters)
+}
+
+fn get_length_bins(matches: &ArgMatches) -> LengthBins {
+    if matches.is_present("logbins") {
+        LengthBins::Log2
+    } else {
+        matches.value_of("binwidth")
+            .and_then(|width| width.parse().ok())
+            .map(LengthBins::Width)
+            .unwrap_or_default()
+    }
+}
+
+fn is_qscore(value: String) -> Result<(), String> {
+    match value.parse::<u32>() {
+        Ok(num) if num <= 93 => Ok(()),
+        _ => Err(format!("{} is not a q-score from 0 to 93", value)),
+    }
+}
+
+fn is_percent(value: String) -> Result<(), String> {
+    match value.parse::<f64>() {
+        Ok(num) if num > 0.0 && num < 100.0 => Ok(()),
+        _ => Err(format!("{} is not a percentage above 0 and below 100", value)),
+    }
+}
+
+fn is_kmer_size(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(k) if (kmers::MIN_K..=kmers::MAX_K).contains(&k) => Ok(()),
+        _ => Err(format!("{} is not a k-mer size from {} to {}", value, kmers::MIN_K, kmers::MAX_K)),
+    }
+}
+
+fn is_positive(value: String) -> Result<(), String> {
+    match value.parse::<u32>() {
+        Ok(num) if num > 0 => Ok(()),
+        _ => Err(format!("{} is not a positive number", value)),
+    }
 }
  
-fn process_dir(entry: &str, query: &str, iscsv: bool, fastq: bool) {
-    let input = PathBuf::from(&entry);
-    let glob = format!("*.{}", query);
-    let path = input.join(glob);
-    input::glob_dir(&path, iscsv, fastq);
+fn process_dir(entry: &str, params: &QcParams, fastq: bool) -> Result<(), QcError> {
+    let path = PathBuf::from(&entry).join("*");
+    input::glob_dir(&path, params, fastq)
 }
 
-fn process_multiple_files(entries: &[&str], iscsv: bool, fastq: bool) {
+fn process_multiple_files(entries: &[&str], 
+                          params: &QcParams, 
+                          fastq: bool
+    ) -> Result<(), QcError> {
     let files: Vec<PathBuf> = entries.iter()
         .map(PathBuf::from).collect();
 
     if fastq {
-        input::par_process_fastq(&files, iscsv);
+        input::par_process_fastq(&files, params)
     } else {
-        input::par_process_fasta(&files, iscsv);
+        input::par_process_fasta(&files, params)
     }
-    
-}
\ No newline at end of file
+}
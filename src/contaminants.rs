@@ -0,0 +1,123 @@
+//! Heru Handika
+//! Built-in adapter, primer, and PhiX sequences
+//! to tag overrepresented reads.
+
+// Shortest overlap between a read and a contaminant.
+const MIN_OVERLAP: usize = 20;
+
+pub struct Contaminant {
+    pub name: &'static str,
+    pub seq: &'static str,
+}
+
+pub const CONTAMINANTS: &[Contaminant] = &[
+    Contaminant {
+        name: "TruSeq Adapter, Read 1",
+        seq: "AGATCGGAAGAGCACACGTCTGAACTCCAGTCAC",
+    },
+    Contaminant {
+        name: "TruSeq Adapter, Read 2",
+        seq: "AGATCGGAAGAGCGTCGTGTAGGGAAAGAGTGT",
+    },
+    Contaminant {
+        name: "TruSeq Universal Adapter",
+        seq: "AATGATACGGCGACCACCGAGATCTACACTCTTTCCCTACACGACGCTCTTCCGATCT",
+    },
+    Contaminant {
+        name: "Illumina P7 Primer",
+        seq: "CAAGCAGAAGACGGCATACGAGAT",
+    },
+    Contaminant {
+        name: "Nextera Transposase, Read 1",
+        seq: "TCGTCGGCAGCGTCAGATGTGTATAAGAGACAG",
+    },
+    Contaminant {
+        name: "Nextera Transposase, Read 2",
+        seq: "GTCTCGTGGGCTCGGAGATGTGTATAAGAGACAG",
+    },
+    Contaminant {
+        name: "Nextera Adapter",
+        seq: "CTGTCTCTTATACACATCT",
+    },
+    Contaminant {
+        name: "Illumina Small RNA 3' Adapter",
+        seq: "TGGAATTCTCGGGTGCCAAGG",
+    },
+    Contaminant {
+        name: "Illumina Small RNA 5' Adapter",
+        seq: "GTTCAGAGTTCTACAGTCCGACGATC",
+    },
+    // The first 2,164 bases of the 5,386 bp PhiX174 genome
+    // (NC_001422). Reads from the rest of the genome are not tagged.
+    Contaminant {
+        name: "PhiX174",
+        seq: "GAGTTTTATCGCTTCCATGACGCAGAAGTTAACACTTTCGGATATTTCTGATGAGTCGAAAAATTATCTTGATAAAGCAGGAATTACTACTGCTTGTTTACGAATTAAATCGAAGTGGACTGCTGGCGGAAAATGAGAAAATTCGACCTATCCTTGCGCAGCTCGAGAAGCTCTTACTTTGCGACCTTTCGCCATCAACTAACGATTCTGTCAAAAACTGACGCGTTGGATGAGGAGAAGTGGCTTAATATGCTTGGCACGTTCGTCAAGGACTGGTTTAGATATGAGTCACATTTTGTTCATGGTAGAGATTCTCTTGTTGACATTTTAAAAGAGCGTGGATTACTATCTGAGTCCGATGCTGTTCAACCACTAATAGGTAAGAAATCATGAGTCAAGTTACTGAACAATCCGTACGTTTCCAGACCGCTTTGGCCTCTATTAAGCTCATTCAGGCTTCTGCCGTTTTGGATTTAACCGAAGATGATTTCGATTTTCTGACGAGTAACAAAGTTTGGATTGCTACTGACCGCTCTCGTGCTCGTCGCTGCGTTGAGGCTTGCGTTTATGGTACGCTGGACTTTGTGGGATACCCTCGCTTTCCTGCTCCTGTTGAGTTTATTGCTGCCGTCATTGCTTATTATGTTCATCCCGTCAACATTCAAACGGCCTGTCTCATCATGGAAGGCGCTGAATTTACGGAAAACATTATTAATGGCGTCGAGCGTCCGGTTAAAGCCGCTGAATTGTTCGCGTTTACCTTGCGTGTACGCGCAGGAAACACTGACGTTCTTACTGACGCAGAAGAAAACGTGCGTCAAAAATTACGTGCGGAAGGAGTGATGTAATGTCTAAAGGTAAAAAACGTTCTGGCGCTCGCCCTGGTCGTCCGCAGCCGTTGCGAGGTACTAAAGGCAAGCGTAAAGGCGCTCGTCTTTGGTATGTAGGTGGTCAACAATTTTAATTGCAGGGGCTTCGGCCCCTTACTTGAGGATAAATTATGTCTAATATTCAAACTGGCGCCGAGCGTATGCCGCATGACCTTTCCCATCTTGGCTTCCTTGCTGGTCAGATTGGTCGTCTTATTACCATTTCAACTACTCCGGTTATCGCTGGCGACTCCTTCGAGATGGACGCCGTTGGCGCTCTCCGTCTTTCTCCATTGCGTCGTGGCCTTGCTATTGACTCTACTGTAGACATTTTTACTTTTTATGTCCCTCATCGTCACGTTTATGGTGAACAGTGGATTAAGTTCATGAAGGATGGTGTTAATGCCACTCCTCTCCCGACTGTTAACACTACTGGTTATATTGACCATGCCGCTTTTCTTGGCACGATTAACCCTGATACCAATAAAATCCCTAAGCATTTGTTTCAGGGTTATTTGAATATCTATAACAACTATTTTAAAGCGCCGTGGATGCCTGACCGTACCGAGGCTAACCCTAATGAGCTTAATCAAGATGATGCTCGTTATGGTTTCCGTTGCTGCCATCTCAAAAACATTTGGACTGCTCCGCTTCCTCCTGAGACTGAGCTTTCTCGCCAAATGACGACTTCTACCACATCTATTGACATTATGGGTCTGCAAGCTGCTTATGCTAATTTGCATACTGACCAAGAACGTGATTACTTCATGCAGCGTTACCATGATGTTATTTCTTCATTTGGAGGTAAAACCTCATATGACGCTGACAACCGTCCTTTACTTGTCATGCGCTCTAATCTCTGGGCATCTGGCTATGATGTTGATGGAACTGACCAAACGTCGTTAGGCCAGTTTTCTGGTCGTGTTCAACAGACCTATAAACATTCTGTGCCGCGTTTCTTTGTTCCTGAGCATGGCACTATGTTTACTCTTGCGCTTGTTCGTTTTCCGCCTACTGCGACTAAAGAGATTCAGTACCTTAACGCTAAAGGTGCTTTGACTTATACCGATATTGCTGGCGACCCTGTTTTGTATGGCAACTTGCCGCCGCGTGAAATTTCTATGAAGGATGTTTTCCGTTCTGGTGATTCGTCTAAGAAGTTTAAGATTGCTGAGGGTCAGTGGTATCGTTATGCGCCTTCGTATGTTTCTCCTGCTTATCACCTTCTTGAAGGCTTCCCATTCATTCAGGAACCGCCTTCTGGTGATTTGCAAGAACGCGCGAAAAAACGTGCG",
+    },
+];
+
+/// Name of the first contaminant sharing at least 20 bases
+/// with the read, on either strand. Contaminants shorter
+/// than that must be fully in the read.
+pub fn find_source(read: &[u8]) -> Option<&'static str> {
+    let read = read.to_ascii_uppercase();
+    let read_rc = reverse_complement(&read);
+
+    CONTAMINANTS.iter()
+        .find(|contaminant| {
+            let seq = contaminant.seq.as_bytes();
+            if seq.len() < MIN_OVERLAP {
+                contains(&read, seq) || contains(&read_rc, seq)
+            } else {
+                shares_kmer(&read, seq) || shares_kmer(&read_rc, seq)
+            }
+        })
+        .map(|contaminant| contaminant.name)
+}
+
+pub fn reverse_complement(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .rev()
+        .map(|base| match base {
+            b'A' => b'T',
+            b'T' => b'A',
+            b'G' => b'C',
+            b'C' => b'G',
+            b'a' => b't',
+            b't' => b'a',
+            b'g' => b'c',
+            b'c' => b'g',
+            _ => *base,
+        })
+        .collect()
+}
+
+// Whether any MIN_OVERLAP-mer of the read is in the contaminant.
+fn shares_kmer(read: &[u8], seq: &[u8]) -> bool {
+    read.windows(MIN_OVERLAP).any(|kmer| contains(seq, kmer))
+}
+
+fn contains(seq: &[u8], sub: &[u8]) -> bool {
+    seq.windows(sub.len()).any(|window| window == sub)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_source_test() {
+        let adapter = b"ttttAGATCGGAAGAGCACACGTCTGAACTCCAGTCACatcg";
+        let nextera = b"GGGGGCTGTCTCTTATACACATCTAAAA";
+        let primer = reverse_complement(b"GGGCAAGCAGAAGACGGCATACGAGATCC");
+        let phix = reverse_complement(b"GAGTTTTATCGCTTCCATGACGCAGAAGTTAACACTTTCGG");
+        let phix_inner = b"AAAAAAACATTTGGACTGCTCCGCTTCCTCCTGAAAAA";
+
+        assert_eq!(Some("TruSeq Adapter, Read 1"), find_source(adapter));
+        assert_eq!(Some("Nextera Adapter"), find_source(nextera));
+        assert_eq!(Some("Illumina P7 Primer"), find_source(&primer));
+        assert_eq!(Some("PhiX174"), find_source(&phix));
+        assert_eq!(Some("PhiX174"), find_source(phix_inner));
+        assert_eq!(None, find_source(b"ACGTACGTACGTACGTACGTACGTACGT"));
+        assert_eq!(None, find_source(b"AGATCGGAAG"));
+    }
+}
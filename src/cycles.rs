@@ -0,0 +1,224 @@
+//! Heru Handika
+//! Per-cycle (read position) statistics.
+//! Reads of different lengths only count
+//! toward the positions they cover.
+
+// Phred scores from 0 to 93 ('~' in Phred+33).
+const QBINS: usize = 94;
+// A, C, G, T, and N (including other ambiguous bases).
+const BASES: usize = 5;
+
+/// Counts of N categories at each read position.
+#[derive(Default)]
+struct PositionCounts<const N: usize> {
+    counts: Vec<[u64; N]>,
+}
+
+impl<const N: usize> PositionCounts<N> {
+    fn add<I: ExactSizeIterator<Item = usize>>(&mut self, bins: I) {
+        if self.counts.len() < bins.len() {
+            self.counts.resize(bins.len(), [0; N]);
+        }
+
+        bins.zip(self.counts.iter_mut())
+            .for_each(|(bin, counts)| counts[bin] += 1);
+    }
+
+    fn merge(&mut self, other: &PositionCounts<N>) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), [0; N]);
+        }
+
+        self.counts.iter_mut()
+            .zip(other.counts.iter())
+            .for_each(|(counts, other)| {
+                counts.iter_mut()
+                    .zip(other.iter())
+                    .for_each(|(count, other)| *count += other);
+            });
+    }
+}
+
+/// Q-score counts at each read position.
+#[derive(Default)]
+pub struct CycleQuality {
+    counts: PositionCounts<QBINS>,
+}
+
+impl CycleQuality {
+    pub fn add(&mut self, phred: &[u8]) {
+        self.counts.add(phred.iter().map(|q| *q as usize));
+    }
+
+    pub fn merge(&mut self, other: &CycleQuality) {
+        self.counts.merge(&other.counts);
+    }
+
+    pub fn summarize(&self) -> Vec<CycleSummary> {
+        self.counts.counts.iter()
+            .enumerate()
+            .map(|(idx, counts)| CycleSummary::new(idx + 1, counts))
+            .collect()
+    }
+}
+
+/// Base counts at each read position.
+#[derive(Default)]
+pub struct CycleBases {
+    counts: PositionCounts<BASES>,
+}
+
+impl CycleBases {
+    pub fn add(&mut self, seq: &[u8]) {
+        self.counts.add(seq.iter().map(|base| base_bin(*base)));
+    }
+
+    pub fn merge(&mut self, other: &CycleBases) {
+        self.counts.merge(&other.counts);
+    }
+
+    pub fn summarize(&self) -> Vec<BaseComposition> {
+        self.counts.counts.iter()
+            .enumerate()
+            .map(|(idx, counts)| BaseComposition::new(idx + 1, counts))
+            .collect()
+    }
+}
+
+fn base_bin(base: u8) -> usize {
+    match base {
+        b'A' | b'a' => 0,
+        b'C' | b'c' => 1,
+        b'G' | b'g' => 2,
+        b'T' | b't' => 3,
+        _ => 4,
+    }
+}
+
+/// Q-score distribution at a read position.
+/// Percentiles use the nearest rank.
+pub struct CycleSummary {
+    pub position: usize,
+    pub reads: u64,
+    pub mean: f64,
+    pub median: u32,
+    pub lower_quartile: u32,
+    pub upper_quartile: u32,
+    pub p10: u32,
+    pub p90: u32,
+}
+
+impl CycleSummary {
+    fn new(position: usize, counts: &[u64; QBINS]) -> Self {
+        let reads: u64 = counts.iter().sum();
+        let sum: u64 = counts.iter()
+            .enumerate()
+            .map(|(q, count)| q as u64 * count)
+            .sum();
+
+        Self {
+            position,
+            reads,
+            mean: sum as f64 / reads as f64,
+            median: percentile(counts, reads, 0.5),
+            lower_quartile: percentile(counts, reads, 0.25),
+            upper_quartile: percentile(counts, reads, 0.75),
+            p10: percentile(counts, reads, 0.1),
+            p90: percentile(counts, reads, 0.9),
+        }
+    }
+}
+
+/// Base fractions at a read position.
+/// Ambiguous bases other than N are counted as N.
+pub struct BaseComposition {
+    pub position: usize,
+    pub reads: u64,
+    pub a: f64,
+    pub c: f64,
+    pub g: f64,
+    pub t: f64,
+    pub n: f64,
+}
+
+impl BaseComposition {
+    fn new(position: usize, counts: &[u64; BASES]) -> Self {
+        let reads: u64 = counts.iter().sum();
+        let fraction = |count: u64| count as f64 / reads as f64;
+
+        Self {
+            position,
+            reads,
+            a: fraction(counts[0]),
+            c: fraction(counts[1]),
+            g: fraction(counts[2]),
+            t: fraction(counts[3]),
+            n: fraction(counts[4]),
+        }
+    }
+}
+
+fn percentile(counts: &[u64; QBINS], total: u64, pct: f64) -> u32 {
+    let rank = ((pct * total as f64).ceil() as u64).max(1);
+    let mut cumsum = 0;
+
+    for (q, count) in counts.iter().enumerate() {
+        cumsum += count;
+        if cumsum >= rank {
+            return q as u32;
+        }
+    }
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle_quality_test() {
+        let mut cycles = CycleQuality::default();
+        let mut short = CycleQuality::default();
+        (1..=10).for_each(|q| cycles.add(&[q, 40]));
+        short.add(&[30]);
+        cycles.merge(&short);
+
+        let summary = cycles.summarize();
+        let first = &summary[0];
+
+        assert_eq!(2, summary.len());
+        assert_eq!(11, first.reads);
+        assert_eq!(85.0 / 11.0, first.mean);
+        assert_eq!(6, first.median);
+        assert_eq!(3, first.lower_quartile);
+        assert_eq!(9, first.upper_quartile);
+        assert_eq!(2, first.p10);
+        assert_eq!(10, first.p90);
+        assert_eq!(10, summary[1].reads);
+        assert_eq!(40, summary[1].median);
+    }
+
+    #[test]
+    fn cycle_bases_test() {
+        let mut cycles = CycleBases::default();
+        let mut other = CycleBases::default();
+        cycles.add(b"ACGT");
+        cycles.add(b"aCNr");
+        other.add(b"GC");
+        other.add(b"T");
+        cycles.merge(&other);
+
+        let summary = cycles.summarize();
+
+        assert_eq!(4, summary.len());
+        assert_eq!(4, summary[0].reads);
+        assert_eq!(0.5, summary[0].a);
+        assert_eq!(0.25, summary[0].g);
+        assert_eq!(0.25, summary[0].t);
+        assert_eq!(1.0, summary[1].c);
+        assert_eq!(0.5, summary[2].n);
+        assert_eq!(2, summary[3].reads);
+        assert_eq!(0.5, summary[3].n);
+    }
+}
@@ -0,0 +1,223 @@
+//! Heru Handika
+//! Sequence duplication levels and overrepresented sequences. 
+//! Reads are counted by the hash of their sequence to save memory.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::contaminants;
+
+// Lower bounds of the duplication levels.
+// The last level holds everything above.
+const DUP_LEVELS: [u64; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 50, 100, 500, 1000, 5000, 10000];
+// Sequences seen fewer times are not kept after merging.
+const MIN_SEQ_COUNT: u64 = 2;
+
+/// Hashes a read sequence. The hasher uses fixed keys,
+/// so hashes from different batches can be merged.
+pub fn hash_seq(seq: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seq.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Number of reads per distinct sequence. A batch keeps
+/// every sequence, but merged counts only keep the sequences
+/// seen at least twice. Singletons cannot be overrepresented.
+#[derive(Default)]
+pub struct DupCounter {
+    counts: HashMap<u64, u64>,
+    seqs: HashMap<u64, Vec<u8>>,
+    reads: u64,
+}
+
+impl DupCounter {
+    pub fn add(&mut self, hash: u64, seq: &[u8]) {
+        *self.counts.entry(hash).or_insert(0) += 1;
+        self.seqs.entry(hash).or_insert_with(|| seq.to_vec());
+        self.reads += 1;
+    }
+
+    pub fn merge(&mut self, other: &DupCounter) {
+        for (hash, count) in other.counts.iter() {
+            let total = self.counts.entry(*hash).or_insert(0);
+            *total += count;
+
+            if *total >= MIN_SEQ_COUNT && !self.seqs.contains_key(hash) {
+                if let Some(seq) = other.seqs.get(hash) {
+                    self.seqs.insert(*hash, seq.clone());
+                }
+            }
+        }
+        self.reads += other.reads;
+
+        let counts = &self.counts;
+        self.seqs.retain(|hash, _| counts[hash] >= MIN_SEQ_COUNT);
+    }
+
+    /// Sequences above the percentage of reads, most common first.
+    pub fn overrepresented(&self, min_percent: f64) -> Vec<Overrepresented> {
+        let mut seqs: Vec<Overrepresented> = self.seqs.iter()
+            .filter_map(|(hash, seq)| {
+                let count = self.counts[hash];
+                let percent = count as f64 / self.reads as f64 * 100.0;
+                (count >= MIN_SEQ_COUNT && percent > min_percent).then(|| Overrepresented {
+                    seq: String::from_utf8_lossy(seq).into_owned(),
+                    count,
+                    percent,
+                    source: contaminants::find_source(seq),
+                })
+            })
+            .collect();
+
+        seqs.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.seq.cmp(&b.seq)));
+
+        seqs
+    }
+
+    pub fn summarize(&self) -> Duplication {
+        let mut levels: Vec<DupLevel> = DUP_LEVELS.iter()
+            .enumerate()
+            .map(|(idx, min)| DupLevel {
+                min: *min,
+                max: DUP_LEVELS.get(idx + 1).map(|next| next - 1),
+                sequences: 0,
+                reads: 0,
+                read_ratio: 0.0,
+            })
+            .collect();
+
+        self.counts.values()
+            .for_each(|count| {
+                let idx = DUP_LEVELS.iter().rposition(|min| count >= min).unwrap();
+                levels[idx].sequences += 1;
+                levels[idx].reads += count;
+            });
+
+        levels.iter_mut()
+            .for_each(|level| level.read_ratio = level.reads as f64 / self.reads as f64);
+
+        Duplication {
+            reads: self.reads,
+            percent_unique: self.counts.len() as f64 / self.reads as f64 * 100.0,
+            levels,
+        }
+    }
+}
+
+pub struct Duplication {
+    /// Reads used to estimate duplication.
+    pub reads: u64,
+    /// Percentage of reads left after removing duplicates.
+    pub percent_unique: f64,
+    pub levels: Vec<DupLevel>,
+}
+
+pub struct Overrepresented {
+    pub seq: String,
+    pub count: u64,
+    pub percent: f64,
+    /// Matching adapter, primer, or PhiX.
+    pub source: Option<&'static str>,
+}
+
+/// Distinct sequences seen from min to max times.
+/// No max for the last level.
+pub struct DupLevel {
+    pub min: u64,
+    pub max: Option<u64>,
+    pub sequences: u64,
+    pub reads: u64,
+    pub read_ratio: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplication_test() {
+        let mut dups = DupCounter::default();
+        let mut other = DupCounter::default();
+        [&b"ACGT"[..], b"ACGT", b"TTTT", b"ACGT"].iter()
+            .for_each(|seq| dups.add(hash_seq(seq), seq));
+        (0..60).for_each(|_| other.add(hash_seq(b"GGGG"), b"GGGG"));
+        other.add(hash_seq(b"TTTT"), b"TTTT");
+        dups.merge(&other);
+
+        let res = dups.summarize();
+
+        assert_eq!(65, res.reads);
+        assert_eq!(3.0 / 65.0 * 100.0, res.percent_unique);
+        assert_eq!(16, res.levels.len());
+        assert_eq!(0, res.levels[0].sequences);
+        assert_eq!(1, res.levels[1].sequences);
+        assert_eq!(3, res.levels[2].reads);
+        assert_eq!((50, Some(99)), (res.levels[10].min, res.levels[10].max));
+        assert_eq!(60, res.levels[10].reads);
+        assert_eq!(None, res.levels[15].max);
+        assert_ne!(hash_seq(b"ACGT"), hash_seq(b"ACGG"));
+    }
+
+    #[test]
+    fn overrepresented_test() {
+        let adapter = b"AGATCGGAAGAGCACACGTCTGAACTCCAGTCAC";
+        let mut batches: Vec<DupCounter> = (0..3).map(|_| DupCounter::default()).collect();
+        batches[0].add(hash_seq(adapter), adapter);
+        batches[0].add(hash_seq(b"AAAA"), b"AAAA");
+        batches[1].add(hash_seq(adapter), adapter);
+        batches[1].add(hash_seq(b"CCCC"), b"CCCC");
+        (0..4).for_each(|_| batches[2].add(hash_seq(b"CCCC"), b"CCCC"));
+        (0..2).for_each(|idx| batches[2].add(hash_seq(&[b'T'; 8][..idx + 2]), &[b'T'; 8][..idx + 2]));
+
+        let mut dups = DupCounter::default();
+        batches.iter().for_each(|batch| dups.merge(batch));
+
+        let res = dups.overrepresented(10.0);
+
+        assert_eq!(2, res.len());
+        assert_eq!("CCCC", res[0].seq);
+        assert_eq!(5, res[0].count);
+        assert_eq!(50.0, res[0].percent);
+        assert_eq!(None, res[0].source);
+        assert_eq!(Some("TruSeq Adapter, Read 1"), res[1].source);
+        assert!(!dups.seqs.contains_key(&hash_seq(b"AAAA")));
+        assert!(dups.overrepresented(50.0).is_empty());
+    }
+
+    #[test]
+    fn overrepresented_across_batches_test() {
+        let mut dups = DupCounter::default();
+        (0..10).for_each(|idx| {
+            let mut batch = DupCounter::default();
+            batch.add(hash_seq(b"GATTACA"), b"GATTACA");
+            (0..9).for_each(|read| {
+                let seq = format!("ACGT{}-{}", idx, read);
+                batch.add(hash_seq(seq.as_bytes()), seq.as_bytes());
+            });
+            dups.merge(&batch);
+        });
+
+        let res = dups.overrepresented(5.0);
+
+        assert_eq!(1, res.len());
+        assert_eq!("GATTACA", res[0].seq);
+        assert_eq!(10, res[0].count);
+        assert_eq!(10.0, res[0].percent);
+        assert_eq!(1, dups.seqs.len());
+    }
+
+    #[test]
+    fn merge_prunes_singletons_test() {
+        let mut batch = DupCounter::default();
+        let mut other = DupCounter::default();
+        batch.add(hash_seq(b"AAAA"), b"AAAA");
+        batch.add(hash_seq(b"CCCC"), b"CCCC");
+        other.add(hash_seq(b"CCCC"), b"CCCC");
+        batch.merge(&other);
+
+        assert!(!batch.seqs.contains_key(&hash_seq(b"AAAA")));
+        assert!(batch.seqs.contains_key(&hash_seq(b"CCCC")));
+    }
+}
@@ -0,0 +1,76 @@
+//! Heru Handika
+//! Error types shared across parsing and IO.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::reader;
+
+#[derive(Debug)]
+pub enum QcError {
+    Io(io::Error),
+    InvalidFastq { record: usize, reason: String },
+    UnsupportedQScore(u8),
+    UnsupportedFormat(PathBuf),
+    EmptyInput,
+    NoMatchingFiles(PathBuf),
+    InvalidPattern { pattern: PathBuf, reason: String },
+    FailedFiles(usize),
+}
+
+impl fmt::Display for QcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QcError::Io(err) => write!(f, "IO ERROR. {}", err),
+            QcError::InvalidFastq { record, reason } =>
+                write!(f, "INVALID FASTQ. {} at record {}", reason, record),
+            QcError::UnsupportedQScore(scr) =>
+                write!(f, "UNSUPPORTED Q-SCORE ENCODING! FOUND '{}'", *scr as char),
+            QcError::UnsupportedFormat(path) =>
+                write!(f, "UNSUPPORTED FILE FORMAT FOR {:?}", path),
+            QcError::EmptyInput => write!(f, "NO SEQUENCES FOUND"),
+            QcError::NoMatchingFiles(path) =>
+                write!(f, "CAN'T FIND MATCHING FILES FOR {:?}", path),
+            QcError::InvalidPattern { pattern, reason } =>
+                write!(f, "INVALID GLOB PATTERN {:?}. {}", pattern, reason),
+            QcError::FailedFiles(count) =>
+                write!(f, "{} FILE(S) FAILED TO PROCESS", count),
+        }
+    }
+}
+
+impl Error for QcError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            QcError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for QcError {
+    fn from(err: io::Error) -> Self {
+        QcError::Io(err)
+    }
+}
+
+/// A file that failed to process in a batch.
+pub struct FailedFile {
+    pub path: String,
+    pub seqname: String,
+    pub error: QcError,
+}
+
+impl FailedFile {
+    pub fn new(input: &Path, error: QcError) -> Self {
+        Self {
+            path: input.parent()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            seqname: reader::sample_name(input),
+            error,
+        }
+    }
+}
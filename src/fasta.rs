@@ -1,63 +1,97 @@
-use std::fs::File;
 use std::io::prelude::*;
 use std::io::{self, BufReader, Lines, Write};
-use std::path::PathBuf;
-
-use flate2::bufread::MultiGzDecoder;
-
-use crate::sequence::{FastaStats, SeqReads};
-
-pub fn process_fasta(input: &PathBuf) -> FastaStats {
-    let file = File::open(input).unwrap();
-    if is_gz_fasta(input) {
-        let read = BufReader::new(file);
-        let decom = MultiGzDecoder::new(read);
-        parse_fasta(decom, input) 
-    } else if is_unzip_fasta(input) {
-        parse_fasta(file, input) 
-    } else {
-        panic!("INVALID FASTA");
-    }
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use crate::error::QcError;
+use crate::reader;
+use crate::sequence::{ContigAccumulator, FastaStats, SeqReads, BATCH_BYTES, BATCH_SIZE};
+
+pub fn process_fasta(input: &Path) -> Result<FastaStats, QcError> {
+    let buff = reader::open_input(input)?;
+    parse_fasta(buff, input)
 }
 
-#[inline(always)]
-fn is_gz_fasta(input: &PathBuf) -> bool {
-    let ext = input.extension().unwrap();
-    
-    ext == "gz" || ext == "gzip"
+fn parse_fasta<R: Read + Send>(file: R, input: &Path) -> Result<FastaStats, QcError> {
+    let stdout = io::stdout();
+    let mut stdbuf = io::BufWriter::new(stdout);
+
+    write!(stdbuf, "Processing {:?}\t", reader::sample_name(input)).unwrap();
+
+    let stats = count_contigs(file, input);
+
+    match stats {
+        Ok(_) => writeln!(stdbuf, "\x1b[0;32mDONE!\x1b[0m").unwrap(),
+        Err(_) => writeln!(stdbuf, "\x1b[0;31mFAILED!\x1b[0m").unwrap(),
+    }
+
+    stats
 }
 
-#[inline(always)]
-fn is_unzip_fasta(input: &PathBuf) -> bool {
-    let ext = input.extension().unwrap();
+fn count_contigs<R: Read + Send>(file: R, input: &Path) -> Result<FastaStats, QcError> {
+    let mut contigs = ContigAccumulator::default();
+    let mut file = Fasta::new(file);
+    let mut batches = read_batches(&mut file)?;
+
+    while !batches.is_empty() {
+        // Reads the next batches while counting the current ones.
+        let (next, counts) = rayon::join(
+            || read_batches(&mut file),
+            || batches.par_iter()
+                .map(|batch| count_batch(batch))
+                .collect::<Vec<ContigAccumulator>>()
+        );
+
+        counts.iter().for_each(|batch| contigs.merge(batch));
+        batches = next?;
+    }
 
-    ext == "fasta" || ext == "fas" || ext == "fa"
+    if contigs.contig_counts == 0 {
+        return Err(QcError::EmptyInput);
+    }
+    
+    Ok(FastaStats::get_stats(input, &contigs))
 }
 
+// Reads a batch of contigs per thread.
+fn read_batches<R: Read>(file: &mut Fasta<R>) -> Result<Vec<Vec<String>>, QcError> {
+    let mut batches = Vec::new();
 
+    while batches.len() < rayon::current_num_threads() {
+        let mut batch = Vec::new();
+        let mut bytes = 0;
 
-fn parse_fasta<R: Read>(file: R, input: &PathBuf) -> FastaStats {
-    let stdout = io::stdout();
-    let mut stdbuf = io::BufWriter::new(stdout);
+        while batch.len() < BATCH_SIZE && bytes < BATCH_BYTES {
+            match file.next() {
+                Some(recs) => {
+                    let recs = recs?;
+                    bytes += recs.len();
+                    batch.push(recs);
+                }
+                None => break,
+            }
+        }
 
-    write!(stdbuf, "Processing {:?}\t",
-        input.file_name().unwrap()).unwrap();
+        if batch.is_empty() {
+            break;
+        }
 
-    let mut contig_counts: u32 = 0;
-    let mut contigs: Vec<SeqReads> = Vec::new();
+        batches.push(batch);
+    }
 
-    let file = Fasta::new(file);
+    Ok(batches)
+}
 
-    file.into_iter()
+fn count_batch(batch: &[String]) -> ContigAccumulator {
+    let mut contigs = ContigAccumulator::default();
+    batch.iter()
         .for_each(|recs| {
-            contig_counts += 1;
-            let reads = SeqReads::get_seq_stats(&recs.as_bytes());
-            contigs.push(reads);
+            let reads = SeqReads::get_seq_stats(recs.as_bytes());
+            contigs.add_contig(&reads);
         });
-        
-    writeln!(stdbuf, "\x1b[0;32mDONE!\x1b[0m").unwrap();
-    
-    FastaStats::get_stats(input, &contig_counts, &contigs)
+
+    contigs
 }
 
 pub struct Fasta<R> {
@@ -78,17 +112,22 @@ impl<R: Read> Fasta<R> {
 }
 
 impl<R: Read> Iterator for Fasta<R> {
-    type Item = String;
+    type Item = io::Result<String>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(Ok(line)) = self.reader.next() {
+        for line in self.reader.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+
             if line.starts_with('>') {
                 if self.id {                    
                     let mut res = String::new();
                     res.push_str(&self.seq);
                     self.id = true;
                     self.seq.clear();
-                    return Some(res);
+                    return Some(Ok(res));
                 } else {
                     self.id = true;
                     self.seq.clear();
@@ -103,7 +142,7 @@ impl<R: Read> Iterator for Fasta<R> {
             self.id = false;
             self.seq.clear();
             self.seq.shrink_to_fit();
-            return Some(res);
+            return Some(Ok(res));
         }
         None
     }
@@ -113,14 +152,15 @@ impl<R: Read> Iterator for Fasta<R> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::path::PathBuf;
 
     #[test]
     fn process_fasta_test() {
         let input = PathBuf::from("test_files/contigs.fasta.gz");
         let in_unzip = PathBuf::from("test_files/contigs.fasta");
 
-        let res = process_fasta(&input);
-        let res_unzip = process_fasta(&in_unzip);
+        let res = process_fasta(&input).unwrap();
+        let res_unzip = process_fasta(&in_unzip).unwrap();
         
         assert_eq!(3, res.contig_counts);
         assert_eq!(3, res_unzip.contig_counts);
@@ -130,35 +170,24 @@ mod test {
     fn process_spaced_fasta_test() {
         let input = PathBuf::from("test_files/contigs_spaced.fasta");
 
-        let res = process_fasta(&input);
+        let res = process_fasta(&input).unwrap();
         
         assert_eq!(3, res.contig_counts);
     }
 
     #[test]
-    fn is_gz_fasta_test() {
-        let fname = PathBuf::from("valid.fasta.gz");
-        let fname_gzip = PathBuf::from("valid.fasta.gzip");
-        assert_eq!(true, is_gz_fasta(&fname));
-        assert_eq!(true, is_gz_fasta(&fname_gzip));
-    }
+    fn process_fasta_error_test() {
+        let fname = PathBuf::from("test_files/contigs.fasta.zip");
+        let res = process_fasta(&fname);
 
-    #[test]
-    fn is_unzip_fasta_test() {
-        let fname = PathBuf::from("valid.fasta");
-        let fname_fs = PathBuf::from("valid.fa");
-        let fname_fas = PathBuf::from("valid.fas");
-
-        assert_eq!(true, is_unzip_fasta(&fname));
-        assert_eq!(true, is_unzip_fasta(&fname_fs));
-        assert_eq!(true, is_unzip_fasta(&fname_fas));
+        assert!(matches!(res, Err(QcError::UnsupportedFormat(_))));
     }
-    
+
     #[test]
-    #[should_panic]
-    fn process_fasta_panic_test() {
-        let fname = PathBuf::from("invalid.fastq");
+    fn process_missing_fasta_error_test() {
+        let fname = PathBuf::from("test_files/missing.fasta");
+        let res = process_fasta(&fname);
 
-        process_fasta(&fname);
+        assert!(matches!(res, Err(QcError::Io(_))));
     }
 }
\ No newline at end of file
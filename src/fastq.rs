@@ -1,131 +1,704 @@
-use std::fs::File;
 use std::io::prelude::*;
-use std::io::{self, BufReader, Write};
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::Path;
 
-use flate2::bufread::MultiGzDecoder;
+use rayon::prelude::*;
 
-use crate::sequence::{FastqStats, SeqReads};
-use crate::qscores::QScore;
+use crate::duplication;
+use crate::error::QcError;
+use crate::header;
+use crate::kmers;
+use crate::pairs::PairStatus;
+use crate::reader;
+use crate::sequence::{FastqStats, ReadAccumulator, ReadInfo, SeqReads, BATCH_BYTES, BATCH_SIZE};
+use crate::params::QcParams;
+use crate::qscores::{QEncoding, QScore};
+use crate::tails;
 
-pub fn process_fastq(input: &PathBuf) -> FastqStats {
-    if is_gunzip(input) {
-        parse_gunzip_fastq(input)
-    } else if is_unzip_fastq(input) {
-        parse_unzip_fastq(input)
+pub fn process_fastq(input: &Path, params: &QcParams) -> Result<FastqStats, QcError> {
+    let buff = reader::open_input(input)?;
+    parse_fastq(buff, input, params)
+}
+
+pub type FastqResult = Result<FastqStats, QcError>;
+
+/// Processes R1 and R2 files together, checking that 
+/// both have the same reads in the same order. 
+/// If one mate fails, the other is still read to the end.
+pub fn process_fastq_pair(r1: &Path, r2: &Path, params: &QcParams) -> (FastqResult, FastqResult) {
+    let (buff1, buff2) = match (reader::open_input(r1), reader::open_input(r2)) {
+        (Ok(buff1), Ok(buff2)) => (buff1, buff2),
+        (Err(err), Ok(buff2)) => return (Err(err), parse_fastq(buff2, r2, params)),
+        (Ok(buff1), Err(err)) => return (parse_fastq(buff1, r1, params), Err(err)),
+        (Err(err1), Err(err2)) => return (Err(err1), Err(err2)),
+    };
+
+    let stdout = io::stdout();
+    let mut outbuff = io::BufWriter::new(stdout);
+
+    write!(outbuff, "Processing {:?} and {:?}\t", 
+        reader::sample_name(r1), reader::sample_name(r2)).unwrap();
+
+    let (res1, res2) = count_pair_reads(buff1, buff2, r1, r2, params);
+
+    if res1.is_ok() && res2.is_ok() {
+        writeln!(outbuff, "\x1b[0;32mDONE!\x1b[0m").unwrap();
     } else {
-        panic!("INVALID FASTQ.");
+        writeln!(outbuff, "\x1b[0;31mFAILED!\x1b[0m").unwrap();
     }
+
+    (res1, res2)
 }
 
-#[inline(always)]
-fn is_gunzip(input: &PathBuf) -> bool {
-    input.extension().unwrap() == "gz"
+fn parse_fastq<R: BufRead + Send>(buff: R, 
+                                  input: &Path, 
+                                  params: &QcParams
+    ) -> Result<FastqStats, QcError> {
+    let stdout = io::stdout();
+    let mut outbuff = io::BufWriter::new(stdout);
+
+    write!(outbuff, "Processing {:?}\t", reader::sample_name(input)).unwrap();
+
+    let all_reads = count_reads(buff, input, params);
+
+    match all_reads {
+        Ok(_) => writeln!(outbuff, "\x1b[0;32mDONE!\x1b[0m").unwrap(),
+        Err(_) => writeln!(outbuff, "\x1b[0;31mFAILED!\x1b[0m").unwrap(),
+    }
+
+    all_reads
 }
 
-fn is_unzip_fastq(input: &PathBuf) -> bool {
-    let ext = input.extension().unwrap();
+fn count_reads<R: BufRead + Send>(buff: R, 
+                                  input: &Path, 
+                                  params: &QcParams
+    ) -> Result<FastqStats, QcError> {
+    let mut reads = ReadAccumulator::default();
+    let mut mates = [ReadAccumulator::default(), ReadAccumulator::default()];
+    let mut interleave = Interleave::default();
+    let mut reader = FastqReader::new(buff);
+
+    let (mut batches, mut res) = read_batches(&mut reader, &mut interleave);
+    let encoding = params.encoding
+        .unwrap_or_else(|| detect_encoding(batches.first()));
+
+    while !batches.is_empty() {
+        let track_mates = interleave.is_possible();
+        // Reads the next batches while counting the current ones.
+        let ((next, next_res), counts) = rayon::join(
+            || match res {
+                Ok(()) => read_batches(&mut reader, &mut interleave),
+                Err(_) => (Vec::new(), Ok(())),
+            },
+            || count_batches(&batches, track_mates, encoding, params)
+        );
+
+        for batch in counts {
+            let batch = batch?;
+            reads.merge(&batch.reads);
+            if track_mates {
+                mates.iter_mut()
+                    .zip(batch.mates.iter())
+                    .for_each(|(mate, counts)| mate.merge(counts));
+            }
+        }
+
+        res?;
+        batches = next;
+        res = next_res;
+    }
+
+    res?;
+
+    if reader.record_count() == 0 {
+        return Err(QcError::EmptyInput);
+    }
+
+    let mut stats = FastqStats::count_all_reads(input, &reads, params);
+    stats.encoding = encoding;
+
+    if interleave.is_interleaved() {
+        interleave.check_complete(reader.record_count())?;
+        stats.set_interleaved_mates(&mates, params);
+    }
 
-    ext == "fastq" || ext == "fq"
+    Ok(stats)
 }
 
-fn parse_gunzip_fastq(input: &PathBuf) -> FastqStats {
-    let file = File::open(input).unwrap();
-    let reader = BufReader::new(file);
-    let decompressor = MultiGzDecoder::new(reader);
-    let buff = BufReader::new(decompressor);
+fn count_pair_reads<R, S>(buff1: R, 
+                          buff2: S, 
+                          r1: &Path, 
+                          r2: &Path,
+                          params: &QcParams
+    ) -> (FastqResult, FastqResult) 
+    where R: BufRead + Send, S: BufRead + Send {
+    let mut mate1 = MateReader::new(buff1);
+    let mut mate2 = MateReader::new(buff2);
+    let mut id_mismatch = None;
+
+    let mut batches = read_pair_batches(&mut mate1, &mut mate2, &mut id_mismatch);
+    let encoding1 = params.encoding
+        .unwrap_or_else(|| detect_encoding(batches.first().map(|(batch1, _)| batch1)));
+    let encoding2 = params.encoding
+        .unwrap_or_else(|| detect_encoding(batches.first().map(|(_, batch2)| batch2)));
+
+    while !batches.is_empty() {
+        let (next, counts) = rayon::join(
+            || read_pair_batches(&mut mate1, &mut mate2, &mut id_mismatch),
+            || {
+                batches.par_iter()
+                    .map(|(batch1, batch2)| {
+                        (batch1.count(false, encoding1, params), 
+                         batch2.count(false, encoding2, params))
+                    })
+                    .collect::<Vec<_>>()
+            }
+        );
+
+        for (counts1, counts2) in counts {
+            mate1.add(counts1);
+            mate2.add(counts2);
+        }
+
+        batches = next;
+    }
+
+    let status = match id_mismatch {
+        Some(record) => PairStatus::IdMismatch { record },
+        None if mate1.record_count() != mate2.record_count() => 
+            PairStatus::CountMismatch { 
+                r1: mate1.record_count() as u64, 
+                r2: mate2.record_count() as u64,
+            },
+        None => PairStatus::Paired,
+    };
 
-    parse_fastq(buff, input)
+    let res1 = mate1.into_stats(r1, encoding1, params);
+    let res2 = mate2.into_stats(r2, encoding2, params);
+
+    match (res1, res2) {
+        (Ok(mut stats1), Ok(mut stats2)) => {
+            stats1.set_mate(r2, status.clone());
+            stats2.set_mate(r1, status);
+            (Ok(stats1), Ok(stats2))
+        }
+        (res1, res2) => (res1, res2),
+    }
 }
 
-fn parse_unzip_fastq(input: &PathBuf) -> FastqStats {
-    let file = File::open(input).unwrap();
-    let buff = BufReader::new(file);
-    
-    parse_fastq(buff, input)
+// One mate of a pair. A mate stops at its first error.
+struct MateReader<R> {
+    reader: FastqReader<R>,
+    reads: ReadAccumulator,
+    read_err: Option<QcError>,
+    // Batches are read ahead of counting, so a counting error
+    // comes before any read error in record order.
+    count_err: Option<QcError>,
 }
 
-fn parse_fastq<R: BufRead>(buff: R, input: &PathBuf) -> FastqStats {
-    let stdout = io::stdout();
-    let mut outbuff = io::BufWriter::new(stdout);
+impl<R: BufRead> MateReader<R> {
+    fn new(buff: R) -> Self {
+        Self {
+            reader: FastqReader::new(buff),
+            reads: ReadAccumulator::default(),
+            read_err: None,
+            count_err: None,
+        }
+    }
+
+    fn is_failed(&self) -> bool {
+        self.read_err.is_some() || self.count_err.is_some()
+    }
+
+    fn record_count(&self) -> usize {
+        self.reader.record_count()
+    }
+
+    // Returns None at the end of the file or after an error.
+    fn next_record(&mut self) -> Option<FastqRecord<'_>> {
+        if self.is_failed() {
+            return None;
+        }
+
+        match self.reader.next_record() {
+            Ok(recs) => recs,
+            Err(err) => {
+                self.read_err = Some(err);
+                None
+            }
+        }
+    }
+
+    fn add(&mut self, counts: Result<BatchCounts, QcError>) {
+        if self.count_err.is_some() {
+            return;
+        }
 
-    write!(outbuff, "Processing {:?}\t", 
-        &input.file_name().unwrap()).unwrap();
-
-    let mut reads: u32 = 0;
-    let mut sq_per_read: Vec<SeqReads> = Vec::new();
-    let mut qscores: Vec<QScore> = Vec::new();
-
-    buff.lines()
-        .filter_map(|ok| ok.ok())
-        .filter(|recs| !recs.is_empty())
-        .enumerate()
-        .for_each(|(idx, recs)| {
-            match idx % 4 {
-                0 => { 
-                    if !&recs.starts_with('@') {
-                    panic!("{:?} IS INVALID FASTQ. \
-                        LOOKING FOR '@' FOUND '{}' at line {}",
-                        input, &recs, &idx + 1);
-                    } else { 
-                        reads += 1 }
-                    },
-
-                1 => {
-                    let reads = SeqReads::get_seq_stats(&recs.trim().as_bytes());
-                    sq_per_read.push(reads);
+        match counts {
+            Ok(counts) => self.reads.merge(&counts.reads),
+            Err(err) => self.count_err = Some(err),
+        }
+    }
+
+    fn into_stats(self, input: &Path, encoding: QEncoding, params: &QcParams) -> FastqResult {
+        if let Some(err) = self.count_err.or(self.read_err) {
+            return Err(err);
+        }
+
+        if self.reader.record_count() == 0 {
+            return Err(QcError::EmptyInput);
+        }
+
+        let mut stats = FastqStats::count_all_reads(input, &self.reads, params);
+        stats.encoding = encoding;
+
+        Ok(stats)
+    }
+}
+
+// Batches read before an error are returned with the error,
+// so errors are reported in record order.
+type BatchRead<T> = (Vec<T>, Result<(), QcError>);
+
+// Reads a batch per thread. Returns no batches 
+// at the end of the file.
+fn read_batches<R: BufRead>(reader: &mut FastqReader<R>, 
+                            interleave: &mut Interleave
+    ) -> BatchRead<RecordBatch> {
+    let mut batches = Vec::new();
+
+    while batches.len() < rayon::current_num_threads() {
+        let mut batch = RecordBatch::new(reader.record_count() + 1);
+        let res = fill_batch(reader, interleave, &mut batch);
+
+        if !batch.is_empty() {
+            batches.push(batch);
+        }
+
+        match res {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(err) => return (batches, Err(err)),
+        }
+    }
+
+    (batches, Ok(()))
+}
+
+// Returns false at the end of the file.
+fn fill_batch<R: BufRead>(reader: &mut FastqReader<R>, 
+                          interleave: &mut Interleave,
+                          batch: &mut RecordBatch
+    ) -> Result<bool, QcError> {
+    while !batch.is_full() {
+        let record = reader.record_count() + 1;
+        match reader.next_record()? {
+            Some(recs) => {
+                interleave.check(recs.header, record)?;
+                batch.push(&recs);
+            }
+            None => return Ok(false),
+        }
+    }
+
+    Ok(true)
+}
+
+// Reads R1 and R2 in lock-step and records the first read ID mismatch.
+// After one mate fails, the other is read alone.
+fn read_pair_batches<R: BufRead, S: BufRead>(mate1: &mut MateReader<R>, 
+                                             mate2: &mut MateReader<S>,
+                                             id_mismatch: &mut Option<u64>
+    ) -> Vec<(RecordBatch, RecordBatch)> {
+    let mut batches = Vec::new();
+
+    while batches.len() < rayon::current_num_threads() {
+        let mut batch1 = RecordBatch::new(mate1.record_count() + 1);
+        let mut batch2 = RecordBatch::new(mate2.record_count() + 1);
+        let more = fill_pair_batch(mate1, mate2, id_mismatch, &mut batch1, &mut batch2);
+
+        if !batch1.is_empty() || !batch2.is_empty() {
+            batches.push((batch1, batch2));
+        }
+
+        if !more {
+            break;
+        }
+    }
+
+    batches
+}
+
+// Returns false at the end of both files.
+fn fill_pair_batch<R: BufRead, S: BufRead>(mate1: &mut MateReader<R>, 
+                                           mate2: &mut MateReader<S>,
+                                           id_mismatch: &mut Option<u64>,
+                                           batch1: &mut RecordBatch,
+                                           batch2: &mut RecordBatch
+    ) -> bool {
+    while !batch1.is_full() && !batch2.is_full() {
+        let record = mate1.record_count() as u64 + 1;
+        match (mate1.next_record(), mate2.next_record()) {
+            (Some(recs1), Some(recs2)) => {
+                if id_mismatch.is_none() 
+                    && header::read_id(recs1.header) != header::read_id(recs2.header) {
+                    *id_mismatch = Some(record);
                 }
-                
-                2 => { 
-                    if !&recs.starts_with('+') {
-                        panic!("{:?} IS INVALID FASTQ. \
-                            LOOKING FOR '+' FOUND '{}' at line {}",
-                            input, &recs, &idx + 1);
-                }},
+                batch1.push(&recs1);
+                batch2.push(&recs2);
+            }
+            (Some(recs1), None) => batch1.push(&recs1),
+            (None, Some(recs2)) => batch2.push(&recs2),
+            (None, None) => return false,
+        }
+    }
 
-                3 => qscores.push(QScore::analyze_qscores(&recs.trim().as_bytes())),
+    true
+}
+
+fn count_batches(batches: &[RecordBatch], 
+                 track_mates: bool, 
+                 encoding: QEncoding, 
+                 params: &QcParams
+    ) -> Vec<Result<BatchCounts, QcError>> {
+    batches.par_iter()
+        .map(|batch| batch.count(track_mates, encoding, params))
+        .collect()
+}
+
+// Detects the q-score encoding from the first batch of reads.
+fn detect_encoding(batch: Option<&RecordBatch>) -> QEncoding {
+    let quals = batch.map(|batch| batch.quals.as_slice()).unwrap_or_default();
+    let min = quals.iter().min();
+    let max = quals.iter().max();
+
+    match (min, max) {
+        (Some(min), Some(max)) => QEncoding::detect(*min, *max),
+        _ => QEncoding::Phred33,
+    }
+}
+
+/// Totals of a record batch, with mate 1 and mate 2
+/// counted separately for interleaved files.
+#[derive(Default)]
+struct BatchCounts {
+    reads: ReadAccumulator,
+    mates: [ReadAccumulator; 2],
+}
+
+/// Headers, sequences, and quality scores copied from 
+/// the reader, so they can be counted on another thread.
+struct RecordBatch {
+    first_record: usize,
+    headers: Vec<u8>,
+    header_ends: Vec<usize>,
+    seqs: Vec<u8>,
+    quals: Vec<u8>,
+    ends: Vec<usize>,
+}
+
+impl RecordBatch {
+    fn new(first_record: usize) -> Self {
+        Self {
+            first_record,
+            headers: Vec::new(),
+            header_ends: Vec::with_capacity(BATCH_SIZE),
+            seqs: Vec::new(),
+            quals: Vec::new(),
+            ends: Vec::with_capacity(BATCH_SIZE),
+        }
+    }
+
+    fn push(&mut self, recs: &FastqRecord) {
+        self.headers.extend_from_slice(recs.header);
+        self.header_ends.push(self.headers.len());
+        self.seqs.extend_from_slice(recs.seq);
+        self.quals.extend_from_slice(recs.qual);
+        self.ends.push(self.seqs.len());
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    fn is_full(&self) -> bool {
+        self.ends.len() >= BATCH_SIZE || self.seqs.len() >= BATCH_BYTES
+    }
+
+    fn count(&self, 
+             track_mates: bool, 
+             encoding: QEncoding, 
+             params: &QcParams
+        ) -> Result<BatchCounts, QcError> {
+        let mut counts = BatchCounts::default();
+        let mut phred = Vec::new();
+        let mut adapter_hits = Vec::with_capacity(params.adapters.len());
+        let mut kmers = Vec::new();
+        let mut start = 0;
+        let mut header_start = 0;
+
+        for (idx, end) in self.ends.iter().enumerate() {
+            let qscores = QScore::analyze_qscores(&self.quals[start..*end], 
+                encoding, params.low_q, &mut phred)?;
+            let record = self.first_record + idx;
+            let seq = &self.seqs[start..*end];
+            let header = &self.headers[header_start..self.header_ends[idx]];
+            adapter_hits.clear();
+            adapter_hits.extend(params.adapters.iter().map(|adapter| adapter.find(seq)));
+            kmers::encode(seq, params.kmer_size, &mut kmers);
+            let read = ReadInfo {
+                seq,
+                phred: &phred,
+                reads: SeqReads::get_seq_stats(seq),
+                qscores,
+                dup_hash: params.in_dup_sample(record).then(|| duplication::hash_seq(seq)),
+                adapter_hits: &adapter_hits,
+                kmers: &kmers,
+                tail: tails::find_tail(seq, params.min_tail),
+                id: header::illumina_id(header),
+                comment: header::casava_comment(header),
+            };
+            counts.reads.add_read(&read);
+
+            if track_mates {
+                let mate = (record - 1) % 2;
+                counts.mates[mate].add_read(&read);
+            }
+
+            start = *end;
+            header_start = self.header_ends[idx];
+        }
+
+        Ok(counts)
+    }
+}
+
+/// Tracks mates in an interleaved FASTQ. Interleaving is 
+/// detected from the first two reads and checked for the rest.
+#[derive(Default)]
+struct Interleave {
+    prev_id: Vec<u8>,
+    first_mate: Option<u8>,
+    interleaved: Option<bool>,
+}
+
+impl Interleave {
+    fn check(&mut self, header: &[u8], record: usize) -> Result<(), QcError> {
+        let id = header::read_id(header);
+        let mate = header::mate(header);
+        let is_first = record % 2 == 1;
+
+        match self.interleaved {
+            None if is_first => {
+                self.prev_id = id.to_vec();
+                self.first_mate = mate;
+            }
+            None => {
+                self.interleaved = Some(self.first_mate == Some(1) 
+                    && mate == Some(2) 
+                    && id == self.prev_id.as_slice());
+            }
+            Some(true) if is_first => {
+                if mate != Some(1) {
+                    return Err(Self::broken(record, "MATE 1"));
+                }
+                self.prev_id.clear();
+                self.prev_id.extend_from_slice(id);
+            }
+            Some(true) => {
+                if mate != Some(2) || id != self.prev_id.as_slice() {
+                    return Err(Self::broken(record, "MATE 2"));
+                }
+            }
+            Some(false) => (),
+        }
+
+        Ok(())
+    }
 
-                _ => panic!("INVALID FASTQ!"),
-        }});
+    fn check_complete(&self, records: usize) -> Result<(), QcError> {
+        if records % 2 == 1 {
+            return Err(Self::broken(records + 1, "MATE 2"));
+        }
 
-    let all_reads = FastqStats::count_all_reads(
-        input, &reads, &sq_per_read, &qscores);
+        Ok(())
+    }
+
+    // True until the reads are known not to be interleaved.
+    fn is_possible(&self) -> bool {
+        self.interleaved != Some(false)
+    }
+
+    fn is_interleaved(&self) -> bool {
+        self.interleaved == Some(true)
+    }
+
+    fn broken(record: usize, mate: &str) -> QcError {
+        QcError::InvalidFastq { 
+            record, 
+            reason: format!("BROKEN INTERLEAVED PAIR. LOOKING FOR {}", mate),
+        }
+    }
+}
+
+/// A single FASTQ record borrowed from the reader buffer.
+/// Trailing whitespace and line endings are stripped.
+#[allow(dead_code)]
+pub struct FastqRecord<'a> {
+    pub header: &'a [u8],
+    pub seq: &'a [u8],
+    pub plus: &'a [u8],
+    pub qual: &'a [u8],
+}
+
+/// Byte-level FASTQ parser. Reads each record into
+/// a reusable buffer, so no allocation per line.
+/// Blank lines are only allowed between records.
+pub struct FastqReader<R> {
+    reader: R,
+    buff: Vec<u8>,
+    lines: [(usize, usize); 4],
+    records: usize,
+}
+
+impl<R: BufRead> FastqReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buff: Vec::new(),
+            lines: [(0, 0); 4],
+            records: 0,
+        }
+    }
+
+    /// Number of records parsed so far.
+    pub fn record_count(&self) -> usize {
+        self.records
+    }
+
+    pub fn next_record(&mut self) -> Result<Option<FastqRecord<'_>>, QcError> {
+        self.buff.clear();
+
+        if !self.read_header()? {
+            return Ok(None);
+        }
+
+        self.records += 1;
+
+        for idx in 1..4 {
+            if !self.read_line(idx)? {
+                return Err(self.invalid(String::from("TRUNCATED RECORD")));
+            }
+        }
         
-    writeln!(outbuff, "\x1b[0;32mDONE!\x1b[0m").unwrap();
-    all_reads
+        self.check_record()?;
+
+        Ok(Some(FastqRecord {
+            header: self.line(0),
+            seq: self.line(1),
+            plus: self.line(2),
+            qual: self.line(3),
+        }))
+    }
+
+    fn read_header(&mut self) -> Result<bool, QcError> {
+        loop {
+            if !self.read_line(0)? {
+                return Ok(false);
+            }
+
+            let (start, end) = self.lines[0];
+            if start < end {
+                return Ok(true);
+            }
+
+            self.buff.clear();
+        }
+    }
+
+    fn read_line(&mut self, idx: usize) -> Result<bool, QcError> {
+        let start = self.buff.len();
+        let bytes = self.reader.read_until(b'\n', &mut self.buff)?;
+
+        if bytes == 0 {
+            return Ok(false);
+        }
+
+        let mut end = self.buff.len();
+        while end > start && self.buff[end - 1].is_ascii_whitespace() {
+            end -= 1;
+        }
+
+        self.lines[idx] = (start, end);
+        Ok(true)
+    }
+
+    #[inline(always)]
+    fn line(&self, idx: usize) -> &[u8] {
+        let (start, end) = self.lines[idx];
+        &self.buff[start..end]
+    }
+
+    fn check_record(&self) -> Result<(), QcError> {
+        if !self.line(0).starts_with(b"@") {
+            return Err(self.invalid(format!("LOOKING FOR '@' FOUND '{}'",
+                String::from_utf8_lossy(self.line(0)))));
+        }
+
+        if !self.line(2).starts_with(b"+") {
+            return Err(self.invalid(format!("LOOKING FOR '+' FOUND '{}'",
+                String::from_utf8_lossy(self.line(2)))));
+        }
+
+        if self.line(1).len() != self.line(3).len() {
+            return Err(self.invalid(format!("SEQUENCE LENGTH ({}) AND \
+                QUALITY LENGTH ({}) DIFFER",
+                self.line(1).len(), self.line(3).len())));
+        }
+
+        Ok(())
+    }
+
+    fn invalid(&self, reason: String) -> QcError {
+        QcError::InvalidFastq { record: self.records, reason }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
 
     #[test]
-    #[should_panic]
-    fn test_parsing_all_panic() {
+    fn test_parsing_all_error() {
         let input = PathBuf::from("test_files/invalid_fastq.fastq.gz");
-        parse_gunzip_fastq(&input);
+        let res = process_fastq(&input, &QcParams::default());
+
+        assert!(matches!(res, Err(QcError::Io(_))));
     }
 
     #[test]
-    #[should_panic(expected = "\"test_files/invalid.fastq.gz\" IS INVALID FASTQ. \
-                                LOOKING FOR '+' FOUND '-' at line 3")]
-    fn test_parsing_invplus_panic() {
+    fn test_parsing_invplus_error() {
         let input = PathBuf::from("test_files/invalid.fastq.gz");
-        parse_gunzip_fastq(&input);
+        let err = process_fastq(&input, &QcParams::default()).err().unwrap();
+
+        assert_eq!("INVALID FASTQ. \
+            LOOKING FOR '+' FOUND '-' at record 1", err.to_string());
     }
 
     #[test]
-    #[should_panic(expected = "\"test_files/invalid2.fastq.gz\" IS INVALID FASTQ. \
-                                LOOKING FOR '@' FOUND 'Bunomys_chrysocomus' at line 9")]
-    fn test_parsing_invname_panic() {
+    fn test_parsing_invname_error() {
         let input = PathBuf::from("test_files/invalid2.fastq.gz");
-        parse_gunzip_fastq(&input);
+        let err = process_fastq(&input, &QcParams::default()).err().unwrap();
+
+        assert_eq!("INVALID FASTQ. \
+            LOOKING FOR '@' FOUND 'Bunomys_chrysocomus' at record 3", err.to_string());
     }
     
     #[test]
     fn parsing_whitespaced_fastq_gz_test() {
         let input = PathBuf::from("test_files/whitespace.fastq.gz");
-        let res = parse_gunzip_fastq(&input);
+        let res = process_fastq(&input, &QcParams::default()).unwrap();
 
         assert_eq!(70, res.total_bp);
         assert_eq!(0, res.sum_low_bases);
@@ -135,7 +708,7 @@ mod tests {
     #[test]
     fn parsing_valid_fastq_qz_test() {
         let input = PathBuf::from("test_files/valid.fastq.gz");
-        let res = parse_gunzip_fastq(&input);
+        let res = process_fastq(&input, &QcParams::default()).unwrap();
 
         assert_eq!(140, res.total_bp);
         assert_eq!(0, res.sum_low_bases);
@@ -146,32 +719,262 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn panic_invalid_fastq_test() {
-        let input = PathBuf::from("valid_input.fasta");
-        process_fastq(&input);
+    fn parsing_bgzf_fastq_test() {
+        let input = PathBuf::from("test_files/valid_bgzf.fastq.gz");
+        let res = process_fastq(&input, &QcParams::default()).unwrap();
+
+        assert_eq!(2, res.read_count);
+        assert_eq!(140, res.total_bp);
+        assert_eq!(64, res.total_gc);
+        assert_eq!(32.0, res.mean_qscores);
+    }
+
+    #[test]
+    fn fastq_reader_test() {
+        let input = b"@read1\nACGT\n+\nIIII\n\n@read2\nAC  \n+read2\nII\r\n";
+        let mut reader = FastqReader::new(&input[..]);
+
+        let first = reader.next_record().unwrap().unwrap();
+        assert_eq!(b"@read1", first.header);
+        assert_eq!(b"ACGT", first.seq);
+        assert_eq!(b"IIII", first.qual);
+
+        let second = reader.next_record().unwrap().unwrap();
+        assert_eq!(b"@read2", second.header);
+        assert_eq!(b"AC", second.seq);
+        assert_eq!(b"+read2", second.plus);
+        assert_eq!(b"II", second.qual);
+
+        assert!(reader.next_record().unwrap().is_none());
+        assert_eq!(2, reader.record_count());
+    }
+
+    #[test]
+    fn fastq_reader_qual_len_error() {
+        let input = b"@read1\nACGT\n+\nIIII\n@read2\nACGT\n+\nIII\n";
+        let mut reader = FastqReader::new(&input[..]);
+        reader.next_record().unwrap();
+        let err = reader.next_record().err().unwrap();
+
+        assert_eq!("INVALID FASTQ. SEQUENCE LENGTH (4) AND \
+            QUALITY LENGTH (3) DIFFER at record 2", err.to_string());
+    }
+
+    #[test]
+    fn fastq_reader_truncated_error() {
+        let input = b"@read1\nACGT\n+\n";
+        let mut reader = FastqReader::new(&input[..]);
+        let err = reader.next_record().err().unwrap();
+
+        assert!(matches!(err, QcError::InvalidFastq { record: 1, .. }));
+    }
+
+    #[test]
+    fn batch_threads_test() {
+        // Spans several batches with varying lengths and q-scores.
+        let mut fq = Vec::new();
+        (0..10_000).for_each(|i| {
+            let len = 50 + i % 37;
+            let seq = "ACGTN".repeat(len / 5 + 1);
+            let qual: String = (0..len).map(|j| (35 + (i + j) % 40) as u8 as char).collect();
+            fq.extend_from_slice(format!("@r{}\n{}\n+\n{}\n", i, &seq[..len], qual).as_bytes());
+        });
+        let input = PathBuf::from("batch.fastq");
+
+        let count = |threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| count_reads(&fq[..], &input, &QcParams::default()).unwrap())
+        };
+        let single = count(1);
+        let multi = count(4);
+
+        assert_eq!(10_000, single.read_count);
+        assert_eq!(single.total_bp, multi.total_bp);
+        assert_eq!(single.total_gc, multi.total_gc);
+        assert_eq!(single.median_reads, multi.median_reads);
+        assert_eq!(single.sd_reads, multi.sd_reads);
+        assert_eq!(single.mean_qscores, multi.mean_qscores);
+        assert_eq!(single.sum_low_bases, multi.sum_low_bases);
+    }
+
+    #[test]
+    fn detect_encoding_test() {
+        let input = PathBuf::from("legacy.fastq");
+        let phred64 = b"@r1\nACGT\n+\nhhBB\n@r2\nACGT\n+\nhhhh\n";
+        let res = count_reads(&phred64[..], &input, &QcParams::default()).unwrap();
+
+        assert_eq!(QEncoding::Phred64, res.encoding);
+        assert_eq!(30.5, res.mean_qscores);
+
+        let params = QcParams { encoding: Some(QEncoding::Phred33), ..QcParams::default() };
+        let res = count_reads(&phred64[..], &input, &params).unwrap();
+
+        assert_eq!(QEncoding::Phred33, res.encoding);
+        assert_eq!(61.5, res.mean_qscores);
+
+        let hifi = b"@r1\nACGT\n+\n??~~\n@r2\nACGT\n+\n~~~~\n";
+        let res = count_reads(&hifi[..], &input, &QcParams::default()).unwrap();
+
+        assert_eq!(QEncoding::Phred33, res.encoding);
+        assert_eq!(77.25, res.mean_qscores);
+    }
+
+    #[test]
+    fn count_pair_reads_test() {
+        let r1 = PathBuf::from("sample_R1.fastq");
+        let r2 = PathBuf::from("sample_R2.fastq");
+        let fq1 = b"@read1/1\nACGT\n+\nIIII\n@read2/1\nACGT\n+\nIIII\n";
+        let fq2 = b"@read1/2\nACGT\n+\nIIII\n@read2/2\nACGT\n+\nIIII\n";
+        let truncated = b"@read1/2\nACGT\n+\nIIII\n";
+        let swapped = b"@read2/2\nACGT\n+\nIIII\n@read1/2\nACGT\n+\nIIII\n";
+        let params = QcParams::default();
+
+        let (res1, res2) = count_pair_reads(&fq1[..], &fq2[..], &r1, &r2, &params);
+        let (res1, res2) = (res1.unwrap(), res2.unwrap());
+        assert_eq!(PairStatus::Paired, res1.pair_status);
+        assert_eq!(Some(String::from("sample_R1.fastq")), res2.mate);
+
+        let (res1, res2) = count_pair_reads(&fq1[..], &truncated[..], &r1, &r2, &params);
+        let (res1, res2) = (res1.unwrap(), res2.unwrap());
+        assert_eq!(PairStatus::CountMismatch { r1: 2, r2: 1 }, res1.pair_status);
+        assert_eq!(2, res1.read_count);
+        assert_eq!(1, res2.read_count);
+
+        let (res1, _) = count_pair_reads(&fq1[..], &swapped[..], &r1, &r2, &params);
+        assert_eq!(PairStatus::IdMismatch { record: 1 }, res1.unwrap().pair_status);
     }
 
     #[test]
-    fn is_gunzip_fastq_test() {
-        let input = PathBuf::from("valid_input.fastq.gz");
-        assert_eq!(true, is_gunzip(&input));
+    fn failed_mate_test() {
+        let r1 = PathBuf::from("sample_R1.fastq");
+        let r2 = PathBuf::from("sample_R2.fastq");
+        let fq1 = b"@read1/1\nACGT\n+\nIIII\n@read2/1\nACGT\n+\nIIII\n";
+        let broken = b"@read1/2\nACGT\n+\nIII\n@read2/2\nACGT\n+\nIIII\n";
+        let params = QcParams::default();
+
+        let (res1, res2) = count_pair_reads(&fq1[..], &broken[..], &r1, &r2, &params);
+        assert_eq!(2, res1.unwrap().read_count);
+        assert!(matches!(res2, Err(QcError::InvalidFastq { record: 1, .. })));
+
+        let (res1, res2) = count_pair_reads(&broken[..], &b""[..], &r1, &r2, &params);
+        assert!(res1.is_err());
+        assert!(matches!(res2, Err(QcError::EmptyInput)));
     }
 
     #[test]
-    fn is_unzip_fastq_test() {
-        let fastq_gz = PathBuf::from("valid_input.fastq.gz");
-        let fastq = PathBuf::from("valid_input.fastq");
-        let fq = PathBuf::from("valid_input.fq");
+    fn interleaved_fastq_test() {
+        let input = PathBuf::from("interleaved.fastq");
+        let fq = b"@r1/1\nACGT\n+\nIIII\n@r1/2\nAC\n+\nII\n\
+                   @r2 1:N:0:1\nACGT\n+\nIIII\n@r2 2:N:0:1\nAC\n+\nII\n";
+        let res = count_reads(&fq[..], &input, &QcParams::default()).unwrap();
 
-        assert_eq!(false, is_unzip_fastq(&fastq_gz));
-        assert_eq!(true, is_unzip_fastq(&fastq));
-        assert_eq!(true, is_unzip_fastq(&fq));
+        assert_eq!(PairStatus::Interleaved, res.pair_status);
+        assert_eq!(4, res.read_count);
+        assert_eq!(2, res.mates.len());
+        assert_eq!(8, res.mates[0].total_bp);
+        assert_eq!(4, res.mates[1].total_bp);
+        assert_eq!("interleaved.fastq (mate 2)", res.mates[1].seqname);
     }
 
     #[test]
-    fn is_unzip_fastq_panic_test() {
-        let invalid = PathBuf::from("invalid_input.fasta");
-        assert_eq!(false, is_unzip_fastq(&invalid));
+    fn tile_quality_fastq_test() {
+        let input = PathBuf::from("tiles.fastq");
+        let fq = b"@A00:1:FC:1:1101:10:20 1:N:0:ACGT\nACGT\n+\nIIII\n\
+                   @A00:1:FC:1:1102:10:20 1:N:0:ACGT\nACGT\n+\n++++\n\
+                   @A00:1:FC:2:1101:10:20 1:N:0:ACGT\nACGT\n+\nIIII\n\
+                   @r4\nACGT\n+\nIIII\n";
+        let res = count_reads(&fq[..], &input, &QcParams::default()).unwrap();
+
+        assert_eq!(2, res.lanes.len());
+        assert_eq!(2, res.lanes[0].reads);
+        assert_eq!(3, res.tiles.len());
+        assert_eq!(1102, res.worst_tile().unwrap().tile);
+        assert_eq!(10.0, res.worst_tile().unwrap().mean_q);
+    }
+
+    #[test]
+    fn mixed_run_fastq_test() {
+        let input = PathBuf::from("runs.fastq");
+        let lanes = b"@A00:1:FC:1:1101:10:20\nACGT\n+\nIIII\n\
+                      @A00:1:FC:2:1101:10:20\nACGT\n+\nIIII\n";
+        let mixed = b"@A00:1:FC:1:1101:10:20\nACGT\n+\nIIII\n\
+                      @A00:1:FC:1:1101:11:20\nACGT\n+\nIIII\n\
+                      @A00:2:FC2:1:1101:10:20\nACGT\n+\nIIII\n";
+
+        let res = count_reads(&lanes[..], &input, &QcParams::default()).unwrap();
+        assert_eq!(2, res.run_lanes.len());
+        assert_eq!(1, res.run_count());
+        assert!(!res.is_mixed_run());
+
+        let res = count_reads(&mixed[..], &input, &QcParams::default()).unwrap();
+        assert_eq!(2, res.run_lanes.len());
+        assert_eq!(2, res.run_lanes[0].reads);
+        assert_eq!("FC2", res.run_lanes[1].flowcell);
+        assert_eq!(2, res.run_count());
+        assert!(res.is_mixed_run());
+    }
+
+    #[test]
+    fn casava_index_fastq_test() {
+        let input = PathBuf::from("indexes.fastq");
+        let fq = b"@A00:1:FC:1:1101:10:20 1:N:0:ACGT\nACGT\n+\nIIII\n\
+                   @A00:1:FC:1:1101:11:20 1:Y:0:ACGT\nACGT\n+\nIIII\n\
+                   @A00:1:FC:1:1101:12:20 1:N:0:ACGA\nACGT\n+\nIIII\n\
+                   @A00:1:FC:1:1101:13:20 1:Y:0:ACGT\nACGT\n+\nIIII\n\
+                   @r5\nACGT\n+\nIIII\n";
+        let res = count_reads(&fq[..], &input, &QcParams::default()).unwrap();
+
+        assert_eq!(4, res.casava_reads);
+        assert_eq!(2, res.filtered_reads);
+        assert_eq!(Some(50.0), res.filtered_percent());
+        assert_eq!(2, res.top_indexes.len());
+        assert_eq!("ACGT", res.top_indexes[0].index);
+        assert_eq!(3, res.top_indexes[0].count);
+        assert_eq!(75.0, res.top_indexes[0].percent);
+        assert_eq!(1, res.top_indexes[1].mismatches);
+    }
+
+    #[test]
+    fn broken_interleaved_fastq_error() {
+        let input = PathBuf::from("interleaved.fastq");
+        let fq = b"@r1/1\nACGT\n+\nIIII\n@r1/2\nAC\n+\nII\n\
+                   @r2/1\nACGT\n+\nIIII\n@r3/2\nAC\n+\nII\n";
+        let odd = b"@r1/1\nACGT\n+\nIIII\n@r1/2\nAC\n+\nII\n\
+                    @r2/1\nACGT\n+\nIIII\n";
+        
+        let err = count_reads(&fq[..], &input, &QcParams::default()).err().unwrap();
+        assert!(matches!(err, QcError::InvalidFastq { record: 4, .. }));
+
+        let err = count_reads(&odd[..], &input, &QcParams::default()).err().unwrap();
+        assert!(matches!(err, QcError::InvalidFastq { record: 4, .. }));
+    }
+
+    #[test]
+    fn single_end_not_interleaved_test() {
+        let input = PathBuf::from("single.fastq");
+        let fq = b"@r1/1\nACGT\n+\nIIII\n@r2/1\nAC\n+\nII\n@r3/1\nAC\n+\nII\n";
+        let res = count_reads(&fq[..], &input, &QcParams::default()).unwrap();
+
+        assert_eq!(PairStatus::SingleEnd, res.pair_status);
+        assert!(res.mates.is_empty());
+    }
+
+    #[test]
+    fn empty_fastq_error() {
+        let input = PathBuf::from("empty.fastq");
+        let res = parse_fastq(&b"\n\n"[..], &input, &QcParams::default());
+
+        assert!(matches!(res, Err(QcError::EmptyInput)));
+    }
+
+    #[test]
+    fn invalid_fastq_error_test() {
+        let input = PathBuf::from("test_files/contigs.fasta");
+        let res = process_fastq(&input, &QcParams::default());
+
+        assert!(matches!(res, Err(QcError::InvalidFastq { record: 1, .. })));
     }
 }
@@ -0,0 +1,167 @@
+//! Heru Handika
+//! Module to parse FASTQ read headers.
+
+/// Fields of a Casava 1.8 read ID
+/// (instrument:run:flowcell:lane:tile:x:y), 
+/// optionally followed by a UMI field.
+#[derive(Debug, PartialEq)]
+pub struct IlluminaId<'a> {
+    pub instrument: &'a [u8],
+    pub run: &'a [u8],
+    pub flowcell: &'a [u8],
+    pub lane: u32,
+    pub tile: u32,
+}
+
+/// Parses the read ID. None if it is not a Casava 1.8 ID.
+pub fn illumina_id(header: &[u8]) -> Option<IlluminaId<'_>> {
+    let mut fields = read_id(header).split(|c| *c == b':');
+    let id = IlluminaId {
+        instrument: fields.next()?,
+        run: fields.next()?,
+        flowcell: fields.next()?,
+        lane: parse_number(fields.next()?)?,
+        tile: parse_number(fields.next()?)?,
+    };
+
+    // Cluster x and y coordinates. Later fields (UMIs) are skipped.
+    parse_number(fields.next()?)?;
+    parse_number(fields.next()?)?;
+
+    Some(id)
+}
+
+/// Fields of a Casava 1.8 comment 
+/// (read:is_filtered:control:index).
+#[derive(Debug, PartialEq)]
+pub struct CasavaComment<'a> {
+    /// Whether the read failed the chastity filter (Y).
+    pub filtered: bool,
+    /// Index sequence, or the sample number in older files.
+    /// Dual indexes are joined by '+'.
+    pub index: &'a [u8],
+}
+
+/// Parses the comment after the read ID. 
+/// None if it is not a Casava 1.8 comment.
+pub fn casava_comment(header: &[u8]) -> Option<CasavaComment<'_>> {
+    let comment = header.split(|c| c.is_ascii_whitespace())
+        .filter(|f| !f.is_empty())
+        .nth(1)?;
+    let mut fields = comment.split(|c| *c == b':');
+
+    parse_number(fields.next()?)?;
+    let filtered = match fields.next()? {
+        b"Y" => true,
+        b"N" => false,
+        _ => return None,
+    };
+    parse_number(fields.next()?)?;
+    let index = fields.next()?;
+
+    fields.next().is_none().then_some(CasavaComment { filtered, index })
+}
+
+fn parse_number(field: &[u8]) -> Option<u32> {
+    std::str::from_utf8(field).ok()?.parse().ok()
+}
+
+/// Read ID without the '@', the comment,
+/// and the /1 or /2 mate suffix.
+pub fn read_id(header: &[u8]) -> &[u8] {
+    let header = header.strip_prefix(b"@").unwrap_or(header);
+    let id = header.split(|c| c.is_ascii_whitespace())
+        .next()
+        .unwrap_or(header);
+
+    if id.ends_with(b"/1") || id.ends_with(b"/2") {
+        &id[..id.len() - 2]
+    } else {
+        id
+    }
+}
+
+/// Mate number from the /1 or /2 suffix 
+/// or the Casava comment (1:N:0:ATCACG).
+pub fn mate(header: &[u8]) -> Option<u8> {
+    let header = header.strip_prefix(b"@").unwrap_or(header);
+    let mut fields = header.split(|c| c.is_ascii_whitespace())
+        .filter(|f| !f.is_empty());
+    let id = fields.next()?;
+
+    if id.ends_with(b"/1") {
+        return Some(1);
+    } else if id.ends_with(b"/2") {
+        return Some(2);
+    }
+
+    match fields.next()? {
+        comment if comment.starts_with(b"1:") => Some(1),
+        comment if comment.starts_with(b"2:") => Some(2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_id_test() {
+        let casava = b"@M00123:12:000-ABCDE:1:1101:15589:1333 1:N:0:ATCACG";
+        let old = b"@HWUSI-EAS100R:6:73:941:1973#0/2";
+        let plain = b"@Bunomys_chrysocomus";
+
+        assert_eq!(b"M00123:12:000-ABCDE:1:1101:15589:1333", read_id(casava));
+        assert_eq!(b"HWUSI-EAS100R:6:73:941:1973#0", read_id(old));
+        assert_eq!(b"Bunomys_chrysocomus", read_id(plain));
+    }
+
+    #[test]
+    fn illumina_id_test() {
+        let casava = b"@M00123:12:000-ABCDE:1:1101:15589:1333 1:N:0:ATCACG";
+        let old = b"@HWUSI-EAS100R:6:73:941:1973#0/2";
+        let sra = b"@SRR001666.1 071112_SLXA-EAS1_s_7:5:1:817:345";
+
+        let id = illumina_id(casava).unwrap();
+
+        assert_eq!(b"M00123", id.instrument);
+        assert_eq!(b"12", id.run);
+        assert_eq!(b"000-ABCDE", id.flowcell);
+        assert_eq!((1, 1101), (id.lane, id.tile));
+        assert_eq!(None, illumina_id(old));
+        assert_eq!(None, illumina_id(sra));
+        assert_eq!(None, illumina_id(b"@A:1:B:1:1101:1"));
+
+        let umi = illumina_id(b"@A00:1:FC:2:1102:10:20:ACGTACGT 1:N:0:ACGT").unwrap();
+        assert_eq!(b"FC", umi.flowcell);
+        assert_eq!((2, 1102), (umi.lane, umi.tile));
+    }
+
+    #[test]
+    fn casava_comment_test() {
+        let dual = b"@A00:1:FC:1:1101:10:20 1:Y:0:ACGTACGT+TTGGCCAA";
+        let number = b"@A00:1:FC:1:1101:10:20 2:N:0:1";
+        let old = b"@HWUSI-EAS100R:6:73:941:1973#0/2";
+
+        let comment = casava_comment(dual).unwrap();
+
+        assert!(comment.filtered);
+        assert_eq!(b"ACGTACGT+TTGGCCAA", comment.index);
+        assert_eq!(Some(CasavaComment { filtered: false, index: b"1" }), casava_comment(number));
+        assert_eq!(None, casava_comment(old));
+        assert_eq!(None, casava_comment(b"@r1 1:X:0:ACGT"));
+        assert_eq!(None, casava_comment(b"@r1 length=150"));
+    }
+
+    #[test]
+    fn mate_test() {
+        let casava = b"@M00123:12:000-ABCDE:1:1101:15589:1333 2:N:0:ATCACG";
+        let old = b"@HWUSI-EAS100R:6:73:941:1973#0/1";
+        let plain = b"@Bunomys_chrysocomus";
+
+        assert_eq!(Some(2), mate(casava));
+        assert_eq!(Some(1), mate(old));
+        assert_eq!(None, mate(plain));
+    }
+}
@@ -0,0 +1,143 @@
+//! Heru Handika
+//! Chastity filter and index sequences from Casava 1.8
+//! comments. Indexes far from the dominant one point to
+//! index hopping or pooling mistakes.
+
+use std::collections::HashMap;
+
+use crate::header::CasavaComment;
+
+// Indexes reported per file.
+const TOP_INDEXES: usize = 10;
+
+/// Reads with a Casava comment, filtered reads, 
+/// and reads per index sequence.
+#[derive(Default)]
+pub struct IndexCounts {
+    reads: u64,
+    filtered: u64,
+    indexes: HashMap<Vec<u8>, u64>,
+}
+
+impl IndexCounts {
+    pub fn add(&mut self, comment: &CasavaComment) {
+        self.reads += 1;
+        if comment.filtered {
+            self.filtered += 1;
+        }
+
+        if comment.index.is_empty() {
+            return;
+        }
+        match self.indexes.get_mut(comment.index) {
+            Some(count) => *count += 1,
+            None => {
+                self.indexes.insert(comment.index.to_vec(), 1);
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &IndexCounts) {
+        self.reads += other.reads;
+        self.filtered += other.filtered;
+        other.indexes.iter()
+            .for_each(|(index, count)| {
+                *self.indexes.entry(index.clone()).or_insert(0) += count;
+            });
+    }
+
+    /// Reads with a Casava comment.
+    pub fn reads(&self) -> u64 {
+        self.reads
+    }
+
+    /// Reads flagged by the chastity filter (Y).
+    pub fn filtered(&self) -> u64 {
+        self.filtered
+    }
+
+    /// Most common indexes, with their mismatches
+    /// to the most common one.
+    pub fn top_indexes(&self) -> Vec<IndexSummary> {
+        let total: u64 = self.indexes.values().sum();
+        let mut indexes: Vec<(&Vec<u8>, &u64)> = self.indexes.iter().collect();
+        indexes.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        indexes.truncate(TOP_INDEXES);
+
+        let dominant = match indexes.first() {
+            Some((index, _)) => index.as_slice(),
+            None => return Vec::new(),
+        };
+
+        indexes.iter()
+            .map(|(index, count)| IndexSummary {
+                index: String::from_utf8_lossy(index).into_owned(),
+                count: **count,
+                percent: **count as f64 / total as f64 * 100.0,
+                mismatches: mismatches(index, dominant),
+            })
+            .collect()
+    }
+}
+
+// Hamming distance. Extra bases count as mismatches.
+fn mismatches(index: &[u8], dominant: &[u8]) -> usize {
+    let diff = index.iter()
+        .zip(dominant.iter())
+        .filter(|(a, b)| !a.eq_ignore_ascii_case(b))
+        .count();
+
+    diff + index.len().abs_diff(dominant.len())
+}
+
+pub struct IndexSummary {
+    pub index: String,
+    pub count: u64,
+    /// Percentage of reads with an index.
+    pub percent: f64,
+    /// Mismatches to the most common index.
+    pub mismatches: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header;
+
+    #[test]
+    fn index_counts_test() {
+        let mut counts = IndexCounts::default();
+        let mut other = IndexCounts::default();
+        let headers: [&[u8]; 6] = [
+            b"@r1 1:N:0:ACGTACGT+TTGGCCAA",
+            b"@r2 1:N:0:ACGTACGT+TTGGCCAA",
+            b"@r3 1:Y:0:ACGTACGT+TTGGCCAA",
+            b"@r4 1:N:0:ACGTACGA+TTGGCCTT",
+            b"@r5 1:Y:0:",
+            b"@r6",
+        ];
+        headers.iter()
+            .enumerate()
+            .filter_map(|(idx, h)| header::casava_comment(h).map(|c| (idx, c)))
+            .for_each(|(idx, comment)| {
+                if idx < 2 {
+                    counts.add(&comment);
+                } else {
+                    other.add(&comment);
+                }
+            });
+        counts.merge(&other);
+
+        let res = counts.top_indexes();
+
+        assert_eq!(5, counts.reads());
+        assert_eq!(2, counts.filtered());
+        assert_eq!(2, res.len());
+        assert_eq!("ACGTACGT+TTGGCCAA", res[0].index);
+        assert_eq!(3, res[0].count);
+        assert_eq!(75.0, res[0].percent);
+        assert_eq!(0, res[0].mismatches);
+        assert_eq!(3, res[1].mismatches);
+        assert_eq!(2, mismatches(b"ACGTAA", b"ACGT"));
+    }
+}
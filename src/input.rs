@@ -2,25 +2,36 @@
 //! Module to process user inputs.
 
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 
 use glob::glob;
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
+use crate::error::{FailedFile, QcError};
 use crate::fasta;
-use crate::fastq;
+use crate::fastq::{self, FastqResult};
+use crate::pairs::{self, FastqInput, PairStatus};
+use crate::params::QcParams;
+use crate::reader;
 use crate::sequence::{FastqStats, FastaStats};
 use crate::output;
 
-pub fn traverse_dir(path: &str, iscsv: bool, fastq: bool) {
+const FASTQ_EXT: [&str; 2] = ["fastq", "fq"];
+const FASTA_EXT: [&str; 3] = ["fasta", "fas", "fa"];
+
+pub fn traverse_dir(path: &str, params: &QcParams, fastq: bool) -> Result<(), QcError> {
     let entries = call_walkdir(path, fastq);
 
+    if entries.is_empty() {
+        return Err(QcError::NoMatchingFiles(PathBuf::from(path)));
+    }
+
     if fastq {
-        par_process_fastq(&entries, iscsv);   
+        par_process_fastq(&entries, params)
     } else {
-        par_process_fasta(&entries, iscsv);
+        par_process_fasta(&entries, params)
     }
 }
 
@@ -45,76 +56,171 @@ pub fn call_walkdir(path: &str, fastq: bool) -> Vec<PathBuf> {
 }
 
 fn match_fastq(files: &str, entries: &mut Vec<PathBuf>) {
-    match files {
-        s if s.ends_with(".fastq.gz") => entries.push(PathBuf::from(files)),
-        s if s.ends_with(".fq.gz") => entries.push(PathBuf::from(files)),
-        s if s.ends_with("fastq.gzip") => entries.push(PathBuf::from(files)),
-        s if s.ends_with("fq.gzip") => entries.push(PathBuf::from(files)),
-        s if s.ends_with("fastq") => entries.push(PathBuf::from(files)),
-        s if s.ends_with("fq") => entries.push(PathBuf::from(files)),
-        _ => (),
-    };
+    if reader::has_seq_extension(Path::new(files), &FASTQ_EXT) {
+        entries.push(PathBuf::from(files));
+    }
 }
 
 fn match_fasta(files: &str, entries: &mut Vec<PathBuf>) {
-    match files {
-        s if s.ends_with(".fasta.gz") => entries.push(PathBuf::from(files)),
-        s if s.ends_with(".fas.gz") => entries.push(PathBuf::from(files)),
-        s if s.ends_with(".fa.gz") => entries.push(PathBuf::from(files)),
-        s if s.ends_with(".fasta.gzip") => entries.push(PathBuf::from(files)),
-        s if s.ends_with(".fa.gzip") => entries.push(PathBuf::from(files)),
-        s if s.ends_with(".fasta") => entries.push(PathBuf::from(files)),
-        s if s.ends_with(".fas") => entries.push(PathBuf::from(files)),
-        s if s.ends_with(".fa") => entries.push(PathBuf::from(files)),
-        _ => (),
-    };
+    if reader::has_seq_extension(Path::new(files), &FASTA_EXT) {
+        entries.push(PathBuf::from(files));
+    }
 }
 
-pub fn glob_dir(path: &PathBuf, iscsv: bool, fastq: bool) {
-    let files = call_glob(path);
+/// Processes sequence files matching a glob pattern. 
+/// Files are filtered by their sequence extension.
+pub fn glob_dir(path: &Path, params: &QcParams, fastq: bool) -> Result<(), QcError> {
+    let mut files = Vec::new();
+    call_glob(path)?.iter()
+        .filter(|file| file.is_file())
+        .for_each(|file| {
+            let file = file.to_string_lossy();
+            if fastq {
+                match_fastq(&file, &mut files);
+            } else {
+                match_fasta(&file, &mut files);
+            }
+        });
 
     if files.is_empty() {
-        panic!("CAN'T FIND MATCHING FILES FOR {:?}.", path);
+        return Err(QcError::NoMatchingFiles(path.to_path_buf()));
     }
 
     if fastq {
-        par_process_fastq(&files, iscsv);
+        par_process_fastq(&files, params)
     } else {
-        par_process_fasta(&files, iscsv)
+        par_process_fasta(&files, params)
     }
 }
 
-pub fn call_glob(path: &PathBuf) -> Vec<PathBuf> {
-    glob(&path.to_string_lossy())
-        .expect("Failed to read files")
+pub fn call_glob(path: &Path) -> Result<Vec<PathBuf>, QcError> {
+    let files = glob(&path.to_string_lossy())
+        .map_err(|err| QcError::InvalidPattern { 
+            pattern: path.to_path_buf(), 
+            reason: err.msg.to_string(),
+        })?
         .filter_map(|recs| recs.ok()) 
-        .collect()
+        .collect();
+
+    Ok(files)
 }
  
-pub fn par_process_fastq(files: &[PathBuf], iscsv: bool) {
+/// Processes FASTQ files in parallel. A "-" input reads 
+/// from standard input and is named after `params.name` if given.
+pub fn par_process_fastq(files: &[PathBuf], params: &QcParams) -> Result<(), QcError> {
+    let inputs = pairs::pair_files(files);
     let (sender, receiver) = channel();
     
-    files.into_par_iter()
+    inputs.par_iter()
         .for_each_with(sender, |s, recs| {
-            s.send(fastq::process_fastq(&recs)).unwrap();
+            process_fastq_input(recs, params)
+                .into_iter()
+                .for_each(|res| s.send(res).unwrap());
         });
     
-    let mut all_reads: Vec<FastqStats> = receiver.iter().collect();
+    let mut all_reads: Vec<FastqStats> = Vec::new();
+    let mut failed: Vec<FailedFile> = Vec::new();
+
+    receiver.iter()
+        .for_each(|(path, res)| match (res, stdin_name(path, params.name.as_deref())) {
+            (Ok(mut stats), Some(name)) => {
+                stats.set_seqname(name);
+                all_reads.push(stats);
+            }
+            (Ok(stats), None) => all_reads.push(stats),
+            (Err(err), name) => failed.push(failed_file(path, err, name)),
+        });
     
-    output::write_fastq(&mut all_reads, iscsv);
+    output::write_fastq(&mut all_reads, &mut failed, params)?;
+
+    check_failures(&failed)
 }
 
-pub fn par_process_fasta(files: &[PathBuf], iscsv: bool) {
+pub fn par_process_fasta(files: &[PathBuf], params: &QcParams) -> Result<(), QcError> {
     let (sender, receiver) = channel();
     
     files.into_par_iter()
         .for_each_with(sender, |s, recs| {
-            s.send(fasta::process_fasta(&recs)).unwrap();
+            s.send((recs, fasta::process_fasta(recs))).unwrap();
         });
     
-    let mut all_reads: Vec<FastaStats> = receiver.iter().collect();
+    let mut all_reads: Vec<FastaStats> = Vec::new();
+    let mut failed: Vec<FailedFile> = Vec::new();
+
+    receiver.iter()
+        .for_each(|(path, res)| match (res, stdin_name(path, params.name.as_deref())) {
+            (Ok(mut stats), Some(name)) => {
+                stats.seqname = name.to_string();
+                all_reads.push(stats);
+            }
+            (Ok(stats), None) => all_reads.push(stats),
+            (Err(err), name) => failed.push(failed_file(path, err, name)),
+        });
     
-    output::write_fasta(&mut all_reads, iscsv);
+    output::write_fasta(&mut all_reads, &mut failed, params.iscsv)?;
+
+    check_failures(&failed)
+}
+
+fn process_fastq_input<'a>(input: &'a FastqInput, 
+                           params: &QcParams
+    ) -> Vec<(&'a Path, Result<FastqStats, QcError>)> {
+    match input {
+        FastqInput::Single(path, status) => {
+            let res = fastq::process_fastq(path, params)
+                .map(|mut stats| {
+                    if stats.pair_status != PairStatus::Interleaved {
+                        stats.pair_status = status.clone();
+                    }
+                    stats
+                });
+            vec![(path, res)]
+        }
+        FastqInput::Pair(r1, r2) => {
+            let (res1, res2) = fastq::process_fastq_pair(r1, r2, params);
+            let (res1, res2) = mark_failed_mate(res1, res2, r1, r2);
+            vec![(r1, res1), (r2, res2)]
+        }
+    }
+}
+
+fn mark_failed_mate(res1: FastqResult, 
+                    res2: FastqResult, 
+                    r1: &Path, 
+                    r2: &Path
+    ) -> (FastqResult, FastqResult) {
+    match (res1, res2) {
+        (Ok(mut stats), Err(err)) => {
+            stats.set_mate(r2, PairStatus::MateFailed);
+            (Ok(stats), Err(err))
+        }
+        (Err(err), Ok(mut stats)) => {
+            stats.set_mate(r1, PairStatus::MateFailed);
+            (Err(err), Ok(stats))
+        }
+        (res1, res2) => (res1, res2),
+    }
+}
+
+// User-supplied sample name for standard input.
+fn stdin_name<'a>(path: &Path, name: Option<&'a str>) -> Option<&'a str> {
+    name.filter(|_| reader::is_stdin(path))
+}
+
+fn failed_file(path: &Path, err: QcError, name: Option<&str>) -> FailedFile {
+    let mut failed = FailedFile::new(path, err);
+    if let Some(name) = name {
+        failed.seqname = name.to_string();
+    }
+    failed
+}
+
+fn check_failures(failed: &[FailedFile]) -> Result<(), QcError> {
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(QcError::FailedFiles(failed.len()))
+    }
 }
 
 #[cfg(test)]
@@ -124,42 +230,60 @@ mod tests {
     #[test]
     fn glob_test() {
         let input = PathBuf::from("test_files/*.fasta");
-        let files = call_glob(&input);
+        let files = call_glob(&input).unwrap();
 
         assert_eq!(4, files.len())
     }
+
+    #[test]
+    fn invalid_glob_test() {
+        let input = PathBuf::from("test_files/***.fasta");
+        let err = call_glob(&input).err().unwrap();
+
+        assert!(matches!(err, QcError::InvalidPattern { .. }));
+    }
     
     #[test]
     fn tranverse_dir_test() {
         let input = "test_files/";
-        let files = call_walkdir (&input, false);
+        let files = call_walkdir (input, false);
 
-        assert_eq!(5, files.len())
+        assert_eq!(8, files.len())
     } 
 
     #[test]
     fn match_fasta_test() {
-        let input = vec!["test.fasta", "test.fas", "test.fa", "test.fa.gz"];
+        let input = [
+            "test_files/contigs.fasta", 
+            "test_files/contigs.fasta.gz", 
+            "test_files/contigs.fasta.bz2", 
+            "test_files/contigs.fasta.zip",
+        ];
         let mut entries = Vec::new();
 
         input.iter()
             .for_each(|e| {
-                match_fasta(&e, &mut entries);
+                match_fasta(e, &mut entries);
             });
 
-        assert_eq!(4, entries.len());
+        assert_eq!(3, entries.len());
     }
 
     #[test]
     fn match_fastq_test() {
-        let input = vec!["test.fq", "test.fastq", "test.fq.gz", "test.fa.gz"];
+        let input = [
+            "test.fq", 
+            "test.fastq", 
+            "test_files/valid.fastq.gz", 
+            "test_files/contigs.fasta.gz",
+        ];
         let mut entries = Vec::new();
 
         input.iter()
             .for_each(|e| {
-                match_fastq(&e, &mut entries);
+                match_fastq(e, &mut entries);
             });
 
         assert_eq!(3, entries.len());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,299 @@
+//! Heru Handika
+//! K-mer content. K-mers are counted at each read position
+//! to find k-mers enriched at specific positions, such as
+//! primers, barcodes, or random priming bias.
+
+use std::f64::consts::PI;
+
+pub const MIN_K: usize = 2;
+// Counts take 4^k values per position bin.
+pub const MAX_K: usize = 8;
+// Enriched k-mers reported per file.
+const MAX_REPORTED: usize = 20;
+// Bonferroni-corrected p-value below which a k-mer is enriched.
+const MAX_P_VALUE: f64 = 0.01;
+
+/// Encodes the k-mer starting at each read position,
+/// two bits per base. K-mers with ambiguous bases are None.
+pub fn encode(seq: &[u8], k: usize, kmers: &mut Vec<Option<u32>>) {
+    kmers.clear();
+    let mask = (1u32 << (2 * k)) - 1;
+    let mut code = 0;
+    let mut valid = 0;
+
+    seq.iter()
+        .enumerate()
+        .for_each(|(idx, base)| {
+            match base_code(*base) {
+                Some(bits) => {
+                    code = ((code << 2) | bits) & mask;
+                    valid += 1;
+                }
+                None => valid = 0,
+            }
+
+            if idx + 1 >= k {
+                kmers.push((valid >= k).then_some(code));
+            }
+        });
+}
+
+fn base_code(base: u8) -> Option<u32> {
+    match base {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' => Some(3),
+        _ => None,
+    }
+}
+
+fn decode(code: usize, k: usize) -> String {
+    (0..k).rev()
+        .map(|idx| match (code >> (2 * idx)) & 3 {
+            0 => 'A',
+            1 => 'C',
+            2 => 'G',
+            _ => 'T',
+        })
+        .collect()
+}
+
+// Start of the position bin after the one starting at start.
+// Bins are one base wide up to position 20,
+// then about a tenth of the position.
+fn next_bin(start: usize) -> usize {
+    start + (start / 10).max(1)
+}
+
+/// K-mer counts in position bins.
+#[derive(Default)]
+pub struct KmerCounter {
+    /// Counts in each bin, indexed by the k-mer code.
+    bins: Vec<Vec<u64>>,
+}
+
+impl KmerCounter {
+    pub fn add(&mut self, kmers: &[Option<u32>]) {
+        let mut bin = 0;
+        let mut bin_end = next_bin(0);
+
+        kmers.iter()
+            .enumerate()
+            .for_each(|(pos, kmer)| {
+                if pos == bin_end {
+                    bin += 1;
+                    bin_end = next_bin(bin_end);
+                }
+
+                if let Some(code) = kmer {
+                    if self.bins.len() <= bin {
+                        self.bins.resize(bin + 1, Vec::new());
+                    }
+                    let counts = &mut self.bins[bin];
+                    let code = *code as usize;
+                    if counts.len() <= code {
+                        counts.resize(code + 1, 0);
+                    }
+                    counts[code] += 1;
+                }
+            });
+    }
+
+    pub fn merge(&mut self, other: &KmerCounter) {
+        if self.bins.len() < other.bins.len() {
+            self.bins.resize(other.bins.len(), Vec::new());
+        }
+
+        self.bins.iter_mut()
+            .zip(other.bins.iter())
+            .for_each(|(counts, other)| {
+                if counts.len() < other.len() {
+                    counts.resize(other.len(), 0);
+                }
+                counts.iter_mut()
+                    .zip(other.iter())
+                    .for_each(|(count, other)| *count += other);
+            });
+    }
+
+    /// K-mers enriched in a position bin, most significant first.
+    /// The expected count in a bin assumes the k-mer is spread
+    /// evenly across positions. Enrichment is tested with
+    /// a Poisson tail and corrected for the number of tests.
+    pub fn enriched(&self, k: usize) -> Vec<EnrichedKmer> {
+        let codes = self.bins.iter().map(Vec::len).max().unwrap_or(0);
+        let mut kmer_totals = vec![0u64; codes];
+        self.bins.iter()
+            .for_each(|counts| {
+                kmer_totals.iter_mut()
+                    .zip(counts.iter())
+                    .for_each(|(total, count)| *total += count);
+            });
+        let bin_totals: Vec<u64> = self.bins.iter().map(|counts| counts.iter().sum()).collect();
+        let total: u64 = bin_totals.iter().sum();
+        let tests = kmer_totals.iter().filter(|count| **count > 0).count() * self.bins.len();
+        let ranges = bin_ranges(self.bins.len());
+
+        let mut kmers: Vec<EnrichedKmer> = kmer_totals.iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .filter_map(|(code, count)| {
+                let mut p_value = 1.0;
+                let mut peak: Option<(f64, usize)> = None;
+
+                self.bins.iter()
+                    .enumerate()
+                    .for_each(|(bin, counts)| {
+                        let obs = counts.get(code).copied().unwrap_or(0);
+                        let expected = *count as f64 * bin_totals[bin] as f64 / total as f64;
+                        if obs as f64 <= expected {
+                            return;
+                        }
+
+                        let p = (poisson_tail(obs, expected) * tests as f64).min(1.0);
+                        let ratio = obs as f64 / expected;
+                        p_value = f64::min(p_value, p);
+                        if p < MAX_P_VALUE && peak.is_none_or(|(max, _)| ratio > max) {
+                            peak = Some((ratio, bin));
+                        }
+                    });
+
+                peak.map(|(max_ratio, bin)| EnrichedKmer {
+                    kmer: decode(code, k),
+                    count: *count,
+                    p_value,
+                    max_ratio,
+                    peak_start: ranges[bin].0,
+                    peak_end: ranges[bin].1,
+                })
+            })
+            .collect();
+
+        kmers.sort_by(|a, b| a.p_value.total_cmp(&b.p_value)
+            .then_with(|| b.max_ratio.total_cmp(&a.max_ratio)));
+        kmers.truncate(MAX_REPORTED);
+
+        kmers
+    }
+}
+
+// First and last read positions (from 1) of each bin.
+fn bin_ranges(bins: usize) -> Vec<(usize, usize)> {
+    let mut start = 0;
+
+    (0..bins)
+        .map(|_| {
+            let end = next_bin(start);
+            let range = (start + 1, end);
+            start = end;
+            range
+        })
+        .collect()
+}
+
+// Upper bound of P(X >= obs) for X ~ Poisson(mean) and obs > mean.
+// Each term after the first shrinks by at least mean / (obs + 1).
+fn poisson_tail(obs: u64, mean: f64) -> f64 {
+    let n = obs as f64;
+    let first = (-mean + n * mean.ln() - ln_factorial(obs)).exp();
+
+    (first * (n + 1.0) / (n + 1.0 - mean)).min(1.0)
+}
+
+// Stirling's series above 20. Error below 1e-6.
+fn ln_factorial(n: u64) -> f64 {
+    if n < 20 {
+        return (2..=n).map(|i| (i as f64).ln()).sum();
+    }
+
+    let n = n as f64;
+    n * n.ln() - n + 0.5 * (2.0 * PI * n).ln() + 1.0 / (12.0 * n)
+}
+
+/// A k-mer enriched at some read positions.
+pub struct EnrichedKmer {
+    pub kmer: String,
+    /// Occurrences at all positions.
+    pub count: u64,
+    /// Corrected p-value of the most significant bin.
+    pub p_value: f64,
+    /// Highest observed over expected count in a significant bin.
+    pub max_ratio: f64,
+    /// Read positions of the bin with the highest ratio.
+    pub peak_start: usize,
+    pub peak_end: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn encode_test() {
+        let mut kmers = Vec::new();
+        encode(b"ACGTNacgta", 3, &mut kmers);
+
+        assert_eq!(8, kmers.len());
+        assert_eq!(Some(0b000110), kmers[0]);
+        assert_eq!(None, kmers[2]);
+        assert_eq!(None, kmers[4]);
+        assert_eq!(kmers[0], kmers[5]);
+        assert_eq!("ACG", decode(kmers[0].unwrap() as usize, 3));
+        assert_eq!("GTA", decode(kmers[7].unwrap() as usize, 3));
+    }
+
+    #[test]
+    fn enriched_kmers_test() {
+        let mut counter = KmerCounter::default();
+        let mut other = KmerCounter::default();
+        let mut kmers = Vec::new();
+        let mut state: u64 = 42;
+
+        (0..500).for_each(|idx| {
+            let mut read: Vec<u8> = (0..40)
+                .map(|_| {
+                    state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                    b"ACGT"[(state >> 62) as usize]
+                })
+                .collect();
+            if idx % 5 == 0 {
+                read[..7].copy_from_slice(b"GATTACA");
+            }
+            encode(&read, 7, &mut kmers);
+            if idx < 250 {
+                counter.add(&kmers);
+            } else {
+                other.add(&kmers);
+            }
+        });
+        counter.merge(&other);
+
+        let res = counter.enriched(7);
+        let motif = res.iter().find(|kmer| kmer.kmer == "GATTACA").unwrap();
+
+        assert!(motif.count >= 100);
+        assert!(motif.p_value < 1e-10);
+        assert_eq!((1, 1), (motif.peak_start, motif.peak_end));
+        assert!(res.iter().all(|kmer| kmer.peak_end <= 3));
+    }
+
+    #[test]
+    fn bin_ranges_test() {
+        let ranges = bin_ranges(23);
+
+        assert_eq!((1, 1), ranges[0]);
+        assert_eq!((20, 20), ranges[19]);
+        assert_eq!((21, 22), ranges[20]);
+        assert_eq!((25, 26), ranges[22]);
+    }
+
+    #[test]
+    fn poisson_tail_test() {
+        assert_approx_eq!(ln_factorial(25), 58.00360522298052, 1e-6);
+        assert!(poisson_tail(10, 1.0) < 1.2e-7);
+        assert!(poisson_tail(10, 1.0) > 1.0e-7);
+        assert!(poisson_tail(11, 10.0) > 0.417);
+    }
+}
@@ -2,15 +2,31 @@
 //! First created: 28 December 2020
 //! Lisence MIT
 
+mod adapters;
+mod bgzf;
 mod cli;
+mod contaminants;
+mod cycles;
+mod duplication;
+mod error;
 mod input;
 mod fasta;
 mod fastq;
+mod header;
+mod indexes;
+mod kmers;
+mod pairs;
+mod params;
+mod reader;
+mod runs;
 mod sequence;
 mod qscores;
 mod stats;
+mod tails;
+mod tiles;
 mod output;
 
+use std::process;
 use std::time::Instant;
 
 use clap::crate_version;
@@ -20,11 +36,16 @@ fn main() {
     
     let start_time = Instant::now();
 
-    cli::get_cli(version);
+    let res = cli::get_cli(version);
 
     let duration = start_time.elapsed();
 
     println!("Execution time: {:?}", &duration);
     println!("\nThank you for using simpleQC v{}! 😊", &version);
+
+    if let Err(err) = res {
+        eprintln!("\x1b[0;31mERROR: {}\x1b[0m", err);
+        process::exit(1);
+    }
 }
 
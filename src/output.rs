@@ -2,38 +2,111 @@ use std::fs::File;
 use std::io::{self, LineWriter, Write};
 use num_format::{Locale, ToFormattedString};
 
-use crate::sequence::{FastqStats, FastaStats};
+use crate::error::{FailedFile, QcError};
+use crate::adapters::Adapter;
+use crate::params::QcParams;
+use crate::sequence::{self, FastqStats, FastaStats};
 
-pub fn write_fastq(results: &mut [FastqStats], iscsv: bool) {
+// Number of stats columns in the csv, excluding path and status.
+const FASTQ_COLUMNS: usize = 39;
+const FASTA_COLUMNS: usize = 18;
+
+pub fn write_fastq(results: &mut [FastqStats], 
+                    failed: &mut [FailedFile], 
+                    params: &QcParams
+    ) -> Result<(), QcError> {
     results.sort_by(|a, b| a.seqname.cmp(&b.seqname));
+    failed.sort_by(|a, b| a.seqname.cmp(&b.seqname));
 
     println!("\n\x1b[1mResults:\x1b[0m");
     results.iter()
             .for_each(|recs| {
-                    write_fastq_console(&recs);
+                    write_fastq_console(recs);
+                    recs.mates.iter().for_each(write_fastq_console);
                 });
     
     println!("Total files: {}", results.len());
+    write_pairs_console(results);
+    write_failed_console(failed);
 
-    if iscsv {
-        write_fastq_csv(results);
+    if params.iscsv {
+        write_fastq_csv(results, failed, params.low_q)?;
+        write_cycle_quality_csv(results)?;
+        write_base_content_csv(results)?;
+        write_length_hist_csv(results)?;
+        write_gc_hist_csv(results)?;
+        write_mean_q_hist_csv(results)?;
+        write_ee_hist_csv(results)?;
+        write_dup_levels_csv(results)?;
+        write_overrepresented_csv(results)?;
+        write_adapter_content_csv(results, &params.adapters)?;
+        write_kmers_csv(results)?;
+        write_lanes_csv(results)?;
+        write_tiles_csv(results)?;
+        write_indexes_csv(results)?;
+        write_runs_csv(results)?;
     }
+
+    Ok(())
 }
 
-pub fn write_fasta(stats: &mut [FastaStats], iscsv: bool) {
+pub fn write_fasta(stats: &mut [FastaStats], 
+                    failed: &mut [FailedFile], 
+                    iscsv: bool
+    ) -> Result<(), QcError> {
     stats.sort_by(|a, b| a.seqname.cmp(&b.seqname));
+    failed.sort_by(|a, b| a.seqname.cmp(&b.seqname));
 
     println!("\n\x1b[1mResults:\x1b[0m");
     stats.iter()
         .for_each(|recs| {
-            write_fasta_console(&recs);
+            write_fasta_console(recs);
         });
     
     println!("Total files: {}", stats.len());
+    write_failed_console(failed);
     
     if iscsv {
-        write_fasta_csv(stats);
+        write_fasta_csv(stats, failed)?;
     }
+
+    Ok(())
+}
+
+fn write_pairs_console(results: &[FastqStats]) {
+    let stdout = io::stdout();
+    let mut buff = io::BufWriter::new(stdout);
+    
+    results.iter()
+        .filter(|r1| r1.mate.as_ref().is_some_and(|mate| r1.seqname < *mate))
+        .for_each(|r1| {
+            let mate = r1.mate.as_ref().unwrap();
+            let r2_reads = results.iter()
+                .find(|r2| r2.seqname == *mate)
+                .map(|r2| r2.read_count.to_formatted_string(&Locale::en))
+                .unwrap_or_else(|| String::from("-"));
+
+            writeln!(buff, "Pair {} + {}\t: {} / {} reads\t{}", 
+                r1.seqname, mate, 
+                r1.read_count.to_formatted_string(&Locale::en), 
+                r2_reads, r1.pair_status).unwrap();
+        });
+}
+
+fn write_failed_console(failed: &[FailedFile]) {
+    if failed.is_empty() {
+        return;
+    }
+
+    let stdout = io::stdout();
+    let mut buff = io::BufWriter::new(stdout);
+
+    writeln!(buff, "\x1b[0;31mFailed files: {}\x1b[0m", failed.len()).unwrap();
+    
+    failed.iter()
+        .for_each(|f| {
+            writeln!(buff, "{}\t: {}", f.seqname, f.error).unwrap();
+        });
 }
 
 fn write_fasta_console(contigs: &FastaStats) {
@@ -117,6 +190,20 @@ fn write_fastq_console(all_reads: &FastqStats) {
     
     writeln!(buff, "GC-content\t\t: {:.2}", 
         &all_reads.gc_content).unwrap();
+
+    writeln!(buff, "GC deviation\t\t: {:.2}%", 
+        &all_reads.gc_deviation).unwrap();
+
+    writeln!(buff, "GC-content (no tails)\t: {:.2}", 
+        &all_reads.gc_content_no_tails).unwrap();
+
+    writeln!(buff, "Poly-G/A/T tails\t: {} / {} / {} reads", 
+        all_reads.poly_g_reads.to_formatted_string(&Locale::en),
+        all_reads.poly_a_reads.to_formatted_string(&Locale::en),
+        all_reads.poly_t_reads.to_formatted_string(&Locale::en)).unwrap();
+
+    writeln!(buff, "Mean tail length\t: {:.2} bp", 
+        &all_reads.mean_tail_len).unwrap();
     
     writeln!(buff, "Total N count\t\t: {}", 
         &all_reads.total_n
@@ -146,21 +233,91 @@ fn write_fastq_console(all_reads: &FastqStats) {
     writeln!(buff, "Median\t\t\t: {:.2} bp", 
         &all_reads.median_reads).unwrap();
     
-    writeln!(buff, "Stdev\t\t\t: {:.2}\n", 
+    writeln!(buff, "Stdev\t\t\t: {:.2}", 
         &all_reads.sd_reads).unwrap();
 
+    writeln!(buff, "Unique reads\t\t: {:.2}% of {} reads", 
+        &all_reads.percent_unique,
+        &all_reads.dup_reads
+        .to_formatted_string(&Locale::en)).unwrap();
+
+    writeln!(buff, "# Overrepresented\t: {}", 
+        &all_reads.overrepresented.len()).unwrap();
+
+    writeln!(buff, "# Enriched k-mers\t: {}", 
+        &all_reads.enriched_kmers.len()).unwrap();
+
+    if let Some(adapter) = all_reads.max_adapter() {
+        writeln!(buff, "Adapter content\t\t: {:.2}% ({})", 
+            adapter.total(), adapter.name).unwrap();
+    }
+    writeln!(buff).unwrap();
+
     //--------------------
     writeln!(buff, "\x1b[0;34mPhred Q-Scores:\x1b[0m").unwrap();
 
+    writeln!(buff, "Encoding\t\t: {}",
+        &all_reads.encoding).unwrap();
+
     writeln!(buff, "Mean\t\t\t: {:.2}",
     &all_reads.mean_qscores).unwrap();
 
-    writeln!(buff, "# Bases < 20\t\t: {}",
+    writeln!(buff, "Error-based mean\t: {:.2}",
+        &all_reads.error_mean_qscores).unwrap();
+
+    writeln!(buff, "Expected errors\t\t: {:.2} per read",
+        &all_reads.mean_expected_errors).unwrap();
+
+    writeln!(buff, "# Bases < {}\t\t: {}",
+        &all_reads.low_q,
         &all_reads.sum_low_bases
         .to_formatted_string(&Locale::en)).unwrap();
 
-    writeln!(buff, "Low Q-score ratio\t: {:.2}\n",
+    writeln!(buff, "Low Q-score ratio\t: {:.2}",
         &all_reads.low_bases_ratio).unwrap();
+
+    writeln!(buff, "Q20 ratio\t\t: {:.4}",
+        &all_reads.q20_ratio).unwrap();
+
+    writeln!(buff, "Q30 ratio\t\t: {:.4}\n",
+        &all_reads.q30_ratio).unwrap();
+
+    //--------------------
+    if !all_reads.lanes.is_empty() || all_reads.casava_reads > 0 {
+        writeln!(buff, "\x1b[0;34mFlowcell:\x1b[0m").unwrap();
+
+        writeln!(buff, "Runs\t\t\t: {}",
+            all_reads.run_count()).unwrap();
+
+        writeln!(buff, "Lanes / tiles\t\t: {} / {}",
+            all_reads.lanes.len(),
+            all_reads.tiles.len()).unwrap();
+
+        if let Some(tile) = all_reads.worst_tile() {
+            writeln!(buff, "Lowest tile\t\t: lane {} tile {} (mean q-score {:.2})",
+                tile.lane, tile.tile, tile.mean_q).unwrap();
+        }
+
+        if let Some(percent) = all_reads.filtered_percent() {
+            writeln!(buff, "Filtered reads\t\t: {:.2}%", percent).unwrap();
+        }
+
+        if let Some(index) = all_reads.top_indexes.first() {
+            writeln!(buff, "Top index\t\t: {} ({:.2}%)", 
+                index.index, index.percent).unwrap();
+        }
+        writeln!(buff).unwrap();
+    }
+
+    //--------------------
+    writeln!(buff, "\x1b[0;34mPaired-end:\x1b[0m").unwrap();
+
+    if let Some(mate) = &all_reads.mate {
+        writeln!(buff, "Mate\t\t\t: {}", mate).unwrap();
+    }
+
+    writeln!(buff, "Pair status\t\t: {}\n", 
+        &all_reads.pair_status).unwrap();
     
     if all_reads.total_bp != all_reads.sum_qlen {
         writeln!(buff, 
@@ -170,30 +327,735 @@ fn write_fastq_console(all_reads: &FastqStats) {
             \x1b[0m\n")
             .unwrap();
     }
+
+    if all_reads.is_gc_unusual() {
+        writeln!(buff, 
+            "\x1b[0;33mWARNING!\n\
+            \x1b[3mThe GC distribution deviates from normal.\n\
+            It may indicate contamination or adapter dimers.\
+            \x1b[0m\n")
+            .unwrap();
+    }
+
+    if all_reads.is_mixed_run() {
+        writeln!(buff, 
+            "\x1b[0;33mWARNING!\n\
+            \x1b[3mThe reads come from {} runs or flowcells.\n\
+            The file may include reads from another sample.\
+            \x1b[0m\n", all_reads.run_count())
+            .unwrap();
+    }
+
+    if all_reads.needs_trimming() {
+        writeln!(buff, 
+            "\x1b[0;33mWARNING!\n\
+            \x1b[3mMore than {}% of reads contain an adapter.\n\
+            Consider trimming the reads before assembly.\
+            \x1b[0m\n", sequence::ADAPTER_WARN)
+            .unwrap();
+    }
     
 }
 
-fn write_fastq_csv(all_reads: &[FastqStats]) {
+fn write_fastq_csv(all_reads: &[FastqStats], 
+                   failed: &[FailedFile], 
+                   low_q: u32
+    ) -> Result<(), QcError> {
     let fname = "sQC-Fastq.csv";
-    let output = File::create(&fname).expect("FILE EXISTS.");
+    let output = File::create(fname)?;
     let mut line = LineWriter::new(output);
-    let path = !all_reads[0].path.is_empty();
+    let path = all_reads.iter().any(|r| !r.path.is_empty())
+        || failed.iter().any(|f| !f.path.is_empty());
 
-    write_fastq_header(&mut line, path);
+    write_fastq_header(&mut line, path, low_q);
     
     all_reads.iter()
     .for_each(|seq| {
-        write_fastq_contents(seq, &mut line, path)
+        write_fastq_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_fastq_contents(mate, &mut line, path));
+    });
+
+    failed.iter()
+    .for_each(|f| {
+        write_failed_contents(f, &mut line, path, FASTQ_COLUMNS)
     });
     
     println!("The result is saved as {}", fname);
+
+    Ok(())
+}
+
+/// Writes the q-score distribution at each read position.
+/// Interleaved mates get their own rows.
+fn write_cycle_quality_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-PerBaseQuality.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Position,\
+        Reads,\
+        Mean q-score,\
+        Median q-score,\
+        Lower quartile,\
+        Upper quartile,\
+        10th percentile,\
+        90th percentile"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_cycle_quality_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_cycle_quality_contents(mate, &mut line, path));
+    });
+
+    println!("The per-cycle quality is saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_cycle_quality_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.cycle_quality.iter()
+        .for_each(|cycle| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{},{},{},{},{},{}",
+                seq.seqname,
+                cycle.position,
+                cycle.reads,
+                cycle.mean,
+                cycle.median,
+                cycle.lower_quartile,
+                cycle.upper_quartile,
+                cycle.p10,
+                cycle.p90,
+            ).unwrap();
+        });
+}
+
+/// Writes the base fractions at each read position.
+fn write_base_content_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-PerBaseContent.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Position,\
+        Reads,\
+        A,\
+        C,\
+        G,\
+        T,\
+        N"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_base_content_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_base_content_contents(mate, &mut line, path));
+    });
+
+    println!("The per-cycle base content is saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_base_content_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.base_content.iter()
+        .for_each(|cycle| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{},{},{},{},{}",
+                seq.seqname,
+                cycle.position,
+                cycle.reads,
+                cycle.a,
+                cycle.c,
+                cycle.g,
+                cycle.t,
+                cycle.n,
+            ).unwrap();
+        });
+}
+
+/// Writes the read length histogram.
+fn write_length_hist_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-ReadLengths.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Min length,\
+        Max length,\
+        Read counts"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_length_hist_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_length_hist_contents(mate, &mut line, path));
+    });
+
+    println!("The read length distribution is saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_length_hist_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.length_hist.iter()
+        .for_each(|bin| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{}",
+                seq.seqname,
+                bin.start,
+                bin.end,
+                bin.count,
+            ).unwrap();
+        });
+}
+
+/// Writes the per-read GC histogram 
+/// and the fitted normal distribution.
+fn write_gc_hist_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-GcContent.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        GC-content (%),\
+        Read counts,\
+        Theoretical read counts"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_gc_hist_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_gc_hist_contents(mate, &mut line, path));
+    });
+
+    println!("The per-read GC distribution is saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_gc_hist_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.gc_hist.iter()
+        .for_each(|bin| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{:.2},{}",
+                seq.seqname,
+                bin.gc,
+                bin.count,
+                bin.theoretical,
+            ).unwrap();
+        });
+}
+
+/// Writes the histogram of per-read mean q-scores.
+fn write_mean_q_hist_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-ReadQuality.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Mean q-score,\
+        Read counts,\
+        Read counts (error-based mean)"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_mean_q_hist_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_mean_q_hist_contents(mate, &mut line, path));
+    });
+
+    println!("The per-read quality distribution is saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_mean_q_hist_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.mean_q_hist.iter()
+        .for_each(|bin| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{}",
+                seq.seqname,
+                bin.q,
+                bin.count,
+                bin.error_count,
+            ).unwrap();
+        });
+}
+
+/// Writes the distribution of per-read expected errors.
+fn write_ee_hist_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-ExpectedErrors.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Min expected errors,\
+        Max expected errors,\
+        Read counts,\
+        Cumulative read ratio"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_ee_hist_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_ee_hist_contents(mate, &mut line, path));
+    });
+
+    println!("The expected errors distribution is saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_ee_hist_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.ee_hist.iter()
+        .for_each(|bin| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{},{}",
+                seq.seqname,
+                bin.min,
+                bin.max.map(|max| max.to_string()).unwrap_or_default(),
+                bin.count,
+                bin.cumulative,
+            ).unwrap();
+        });
 }
 
-fn write_fasta_csv(stats: &[FastaStats]) {
+/// Writes the number of sequences at each duplication level.
+fn write_dup_levels_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-Duplication.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Min duplication level,\
+        Max duplication level,\
+        Distinct sequences,\
+        Read counts,\
+        Read ratio"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_dup_levels_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_dup_levels_contents(mate, &mut line, path));
+    });
+
+    println!("The duplication levels are saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_dup_levels_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.dup_levels.iter()
+        .for_each(|level| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{},{},{}",
+                seq.seqname,
+                level.min,
+                level.max.map(|max| max.to_string()).unwrap_or_default(),
+                level.sequences,
+                level.reads,
+                level.read_ratio,
+            ).unwrap();
+        });
+}
+
+/// Writes the overrepresented sequences and their possible sources.
+fn write_overrepresented_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-Overrepresented.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Sequence,\
+        Read counts,\
+        Percentage,\
+        Possible source"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_overrepresented_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_overrepresented_contents(mate, &mut line, path));
+    });
+
+    println!("The overrepresented sequences are saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_overrepresented_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.overrepresented.iter()
+        .for_each(|overrep| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{},\"{}\"",
+                seq.seqname,
+                overrep.seq,
+                overrep.count,
+                overrep.percent,
+                overrep.source.unwrap_or("No hit"),
+            ).unwrap();
+        });
+}
+
+/// Writes the cumulative percentage of reads with each adapter
+/// at each read position. One column per adapter.
+fn write_adapter_content_csv(all_reads: &[FastqStats], 
+                             adapters: &[Adapter]
+    ) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-AdapterContent.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    write!(line, "Sequence names,Position").unwrap();
+    adapters.iter()
+        .for_each(|adapter| write!(line, ",\"{}\"", adapter.name.replace('"', "'")).unwrap());
+    writeln!(line).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_adapter_content_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_adapter_content_contents(mate, &mut line, path));
+    });
+
+    println!("The adapter content is saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_adapter_content_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    let positions = seq.adapter_content.first().map_or(0, |adapter| adapter.content.len());
+
+    (0..positions)
+        .for_each(|pos| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            write!(line, "{},{}", seq.seqname, pos + 1).unwrap();
+            seq.adapter_content.iter()
+                .for_each(|adapter| write!(line, ",{}", adapter.content[pos]).unwrap());
+            writeln!(line).unwrap();
+        });
+}
+
+/// Writes the k-mers enriched at some read positions.
+fn write_kmers_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-Kmers.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        K-mer,\
+        Counts,\
+        P-value,\
+        Max obs/exp,\
+        Peak start,\
+        Peak end"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_kmers_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_kmers_contents(mate, &mut line, path));
+    });
+
+    println!("The enriched k-mers are saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_kmers_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.enriched_kmers.iter()
+        .for_each(|kmer| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{},{},{},{}",
+                seq.seqname,
+                kmer.kmer,
+                kmer.count,
+                kmer.p_value,
+                kmer.max_ratio,
+                kmer.peak_start,
+                kmer.peak_end,
+            ).unwrap();
+        });
+}
+
+/// Writes the read counts and mean q-scores of each lane.
+fn write_lanes_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-Lanes.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Lane,\
+        Read counts,\
+        Mean q-score"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_lanes_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_lanes_contents(mate, &mut line, path));
+    });
+
+    println!("The per-lane quality is saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_lanes_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.lanes.iter()
+        .for_each(|lane| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{}",
+                seq.seqname,
+                lane.lane,
+                lane.reads,
+                lane.mean_q,
+            ).unwrap();
+        });
+}
+
+/// Writes the read counts and mean q-scores of each tile
+/// and their difference from the mean q-score of the file.
+fn write_tiles_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-Tiles.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Lane,\
+        Tile,\
+        Read counts,\
+        Mean q-score,\
+        Difference from file mean"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_tiles_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_tiles_contents(mate, &mut line, path));
+    });
+
+    println!("The per-tile quality is saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_tiles_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.tiles.iter()
+        .for_each(|tile| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{},{},{}",
+                seq.seqname,
+                tile.lane,
+                tile.tile,
+                tile.reads,
+                tile.mean_q,
+                tile.mean_q - seq.mean_qscores,
+            ).unwrap();
+        });
+}
+
+/// Writes the most common index sequences
+/// and their mismatches to the top index.
+fn write_indexes_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-Indexes.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Index,\
+        Read counts,\
+        Percentage,\
+        Mismatches to top index"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_indexes_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_indexes_contents(mate, &mut line, path));
+    });
+
+    println!("The index sequences are saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_indexes_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.top_indexes.iter()
+        .for_each(|index| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{},{}",
+                seq.seqname,
+                index.index,
+                index.count,
+                index.percent,
+                index.mismatches,
+            ).unwrap();
+        });
+}
+
+/// Writes the reads from each instrument, run,
+/// flowcell, and lane combination.
+fn write_runs_csv(all_reads: &[FastqStats]) -> Result<(), QcError> {
+    let fname = "sQC-Fastq-Runs.csv";
+    let output = File::create(fname)?;
+    let mut line = LineWriter::new(output);
+    let path = all_reads.iter().any(|r| !r.path.is_empty());
+
+    if path {
+        write!(line, "Path,").unwrap();
+    }
+    writeln!(line, 
+        "Sequence names,\
+        Instrument,\
+        Run,\
+        Flowcell,\
+        Lane,\
+        Read counts"
+    ).unwrap();
+
+    all_reads.iter()
+    .for_each(|seq| {
+        write_runs_contents(seq, &mut line, path);
+        seq.mates.iter()
+            .for_each(|mate| write_runs_contents(mate, &mut line, path));
+    });
+
+    println!("The runs and lanes are saved as {}", fname);
+
+    Ok(())
+}
+
+fn write_runs_contents<W: Write>(seq: &FastqStats, line: &mut W, path: bool) {
+    seq.run_lanes.iter()
+        .for_each(|lane| {
+            if path {
+                write!(line, "{},", seq.path).unwrap();
+            }
+            writeln!(line, "{},{},{},{},{},{}",
+                seq.seqname,
+                lane.instrument,
+                lane.run,
+                lane.flowcell,
+                lane.lane,
+                lane.reads,
+            ).unwrap();
+        });
+}
+
+fn write_fasta_csv(stats: &[FastaStats], failed: &[FailedFile]) -> Result<(), QcError> {
     let fname = "sQC-Fasta.csv";
-    let output = File::create(&fname).expect("FILE EXISTS.");
+    let output = File::create(fname)?;
     let mut line = LineWriter::new(output);
-    let path = !stats[0].path.is_empty();
+    let path = stats.iter().any(|s| !s.path.is_empty())
+        || failed.iter().any(|f| !f.path.is_empty());
 
     write_fasta_header(&mut line, path);
     
@@ -201,11 +1063,35 @@ fn write_fasta_csv(stats: &[FastaStats]) {
     .for_each(|seq| {
         write_fasta_contents(seq, &mut line, path)
     });
+
+    failed.iter()
+    .for_each(|f| {
+        write_failed_contents(f, &mut line, path, FASTA_COLUMNS)
+    });
     
     println!("The result is saved as {}", fname);
+
+    Ok(())
+}
+
+/// Writes a failed file as a row with empty stats
+/// and the error in the status column.
+fn write_failed_contents<W: Write>(failed: &FailedFile, 
+                                    line: &mut W, 
+                                    path: bool, 
+                                    columns: usize) {
+    if path {
+        write!(line, "{},", failed.path).unwrap();
+    }
+    write!(line, "{}", failed.seqname).unwrap();
+
+    (1..columns).for_each(|_| write!(line, ",").unwrap());
+    
+    writeln!(line, ",\"FAILED: {}\"", 
+        failed.error.to_string().replace('"', "'")).unwrap();
 }
 
-fn write_fastq_header<W: Write>(line:&mut W, path: bool) {
+fn write_fastq_header<W: Write>(line:&mut W, path: bool, low_q: u32) {
     if path {
         write!(line, "Path,").unwrap();
     }
@@ -215,6 +1101,13 @@ fn write_fastq_header<W: Write>(line:&mut W, path: bool) {
         Total sequence length,\
         GC counts,\
         GC-content,\
+        GC deviation,\
+        GC distribution,\
+        GC-content excluding tails,\
+        Poly-G reads,\
+        Poly-A reads,\
+        Poly-T reads,\
+        Mean tail length,\
         N counts,\
         N-content,\
         Min read length,\
@@ -222,9 +1115,28 @@ fn write_fastq_header<W: Write>(line:&mut W, path: bool) {
         Mean read length,\
         Median read length,\
         Stdev read length,\
+        Unique reads (%),\
+        Duplication sample,\
+        # Overrepresented sequences,\
+        Max adapter content (%),\
+        # Enriched k-mers,\
         Mean q-score,\
-        # Low base < 20,\
-        # Low q-score ratio"
+        Error-based mean q-score,\
+        Mean expected errors,\
+        # Low base < {},\
+        # Low q-score ratio,\
+        Q20 ratio,\
+        Q30 ratio,\
+        Q-score encoding,\
+        # Runs,\
+        # Lanes,\
+        # Tiles,\
+        Filtered reads (%),\
+        Top index,\
+        Mate file,\
+        Pair status,\
+        Status",
+        low_q
     ).unwrap();
 }
 
@@ -232,12 +1144,19 @@ fn write_fastq_contents<W: Write>(seq: &FastqStats, line:&mut W, path: bool) {
     if path {
         write!(line, "{},", seq.path).unwrap();
     }
-    writeln!(line, "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}", 
+    writeln!(line, "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},OK", 
         seq.seqname,
         seq.read_count,
         seq.total_bp,
         seq.total_gc, 
         seq.gc_content,
+        seq.gc_deviation,
+        if seq.is_gc_unusual() { "Unusual" } else { "Normal" },
+        seq.gc_content_no_tails,
+        seq.poly_g_reads,
+        seq.poly_a_reads,
+        seq.poly_t_reads,
+        seq.mean_tail_len,
         seq.total_n, 
         seq.n_content,
         seq.min_reads,
@@ -245,9 +1164,26 @@ fn write_fastq_contents<W: Write>(seq: &FastqStats, line:&mut W, path: bool) {
         seq.mean_reads,
         seq.median_reads,
         seq.sd_reads,
+        seq.percent_unique,
+        seq.dup_reads,
+        seq.overrepresented.len(),
+        seq.max_adapter().map_or(0.0, |adapter| adapter.total()),
+        seq.enriched_kmers.len(),
         seq.mean_qscores,
+        seq.error_mean_qscores,
+        seq.mean_expected_errors,
         seq.sum_low_bases,
         seq.low_bases_ratio,
+        seq.q20_ratio,
+        seq.q30_ratio,
+        seq.encoding,
+        seq.run_count(),
+        seq.lanes.len(),
+        seq.tiles.len(),
+        seq.filtered_percent().map(|percent| percent.to_string()).unwrap_or_default(),
+        seq.top_indexes.first().map(|index| index.index.as_str()).unwrap_or_default(),
+        seq.mate.as_deref().unwrap_or_default(),
+        seq.pair_status,
     ).unwrap();
 }
 
@@ -273,7 +1209,8 @@ fn write_fasta_header<W: Write>(line:&mut W, path: bool) {
         N90,\
         #_contigs_>750bp,\
         #_contigs_>1000bp,\
-        #_contigs_>1500bp"
+        #_contigs_>1500bp,\
+        Status"
     ).unwrap();
 }
 
@@ -281,7 +1218,7 @@ fn write_fasta_contents<W: Write>(seq: &FastaStats, line:&mut W, path: bool) {
     if path {
         write!(line, "{},", seq.path).unwrap();
     }
-    writeln!(line, "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}", 
+    writeln!(line, "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},OK", 
         seq.seqname,
         seq.contig_counts,
         seq.total_bp,
@@ -0,0 +1,155 @@
+//! Heru Handika
+//! Module to pair R1 and R2 FASTQ files.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// Illumina naming for read 1 and read 2 files.
+const MATE_TOKENS: [(&str, &str); 3] = [
+    ("_R1_", "_R2_"),
+    ("_R1.", "_R2."),
+    ("_1.", "_2."),
+];
+
+pub enum FastqInput {
+    Single(PathBuf, PairStatus),
+    Pair(PathBuf, PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PairStatus {
+    SingleEnd,
+    MissingMate,
+    MateFailed,
+    Paired,
+    Interleaved,
+    CountMismatch { r1: u64, r2: u64 },
+    IdMismatch { record: u64 },
+}
+
+impl fmt::Display for PairStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PairStatus::SingleEnd => write!(f, "Single-end"),
+            PairStatus::MissingMate => write!(f, "Missing mate"),
+            PairStatus::MateFailed => write!(f, "Mate failed"),
+            PairStatus::Paired => write!(f, "OK"),
+            PairStatus::Interleaved => write!(f, "Interleaved"),
+            PairStatus::CountMismatch { r1, r2 } =>
+                write!(f, "Read count mismatch R1={} R2={}", r1, r2),
+            PairStatus::IdMismatch { record } =>
+                write!(f, "Read ID mismatch at record {}", record),
+        }
+    }
+}
+
+/// Groups files into R1/R2 pairs. Files that do not
+/// follow the naming or have no mate stay single.
+pub fn pair_files(files: &[PathBuf]) -> Vec<FastqInput> {
+    let mut mates: BTreeMap<String, [Option<PathBuf>; 2]> = BTreeMap::new();
+    let mut inputs = Vec::new();
+
+    files.iter()
+        .for_each(|file| {
+            match mate_key(file) {
+                Some((key, mate)) => {
+                    let pair = mates.entry(key).or_insert([None, None]);
+                    if pair[mate].is_none() {
+                        pair[mate] = Some(file.clone());
+                    } else {
+                        inputs.push(FastqInput::Single(
+                            file.clone(), PairStatus::MissingMate));
+                    }
+                }
+                None => inputs.push(FastqInput::Single(
+                    file.clone(), PairStatus::SingleEnd)),
+            }
+        });
+
+    mates.into_iter()
+        .for_each(|(_, pair)| {
+            match pair {
+                [Some(r1), Some(r2)] => inputs.push(FastqInput::Pair(r1, r2)),
+                [Some(file), None] | [None, Some(file)] =>
+                    inputs.push(FastqInput::Single(file, PairStatus::MissingMate)),
+                [None, None] => (),
+            }
+        });
+
+    inputs
+}
+
+// Returns the file path with the mate token masked
+// and the mate index (0 for R1, 1 for R2).
+// Uses the rightmost token in the file name.
+fn mate_key(input: &Path) -> Option<(String, usize)> {
+    let fname = input.file_name()?.to_string_lossy();
+    let mut found: Option<(usize, usize, usize)> = None;
+
+    MATE_TOKENS.iter()
+        .enumerate()
+        .for_each(|(idx, tokens)| {
+            [tokens.0, tokens.1].iter()
+                .enumerate()
+                .for_each(|(mate, token)| {
+                    if let Some(pos) = fname.rfind(token) {
+                        if found.is_none_or(|(p, ..)| pos > p) {
+                            found = Some((pos, idx, mate));
+                        }
+                    }
+                });
+        });
+
+    let (pos, idx, mate) = found?;
+    let token_len = MATE_TOKENS[idx].0.len();
+    let masked = format!("{}<{}>{}",
+        &fname[..pos], idx, &fname[pos + token_len..]);
+    let key = input.with_file_name(masked);
+
+    Some((key.to_string_lossy().into_owned(), mate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mate_key_test() {
+        let r1 = PathBuf::from("data/sample_S1_L001_R1_001.fastq.gz");
+        let r2 = PathBuf::from("data/sample_S1_L001_R2_001.fastq.gz");
+        let sra = PathBuf::from("SRR000001_2.fastq");
+        let single = PathBuf::from("contigs.fastq");
+
+        assert_eq!(mate_key(&r1).unwrap().0, mate_key(&r2).unwrap().0);
+        assert_eq!(0, mate_key(&r1).unwrap().1);
+        assert_eq!(1, mate_key(&r2).unwrap().1);
+        assert_eq!(1, mate_key(&sra).unwrap().1);
+        assert!(mate_key(&single).is_none());
+    }
+
+    #[test]
+    fn pair_files_test() {
+        let files = vec![
+            PathBuf::from("a_R1.fq.gz"),
+            PathBuf::from("a_R2.fq.gz"),
+            PathBuf::from("b_1.fastq"),
+            PathBuf::from("c.fastq"),
+        ];
+
+        let inputs = pair_files(&files);
+        let pairs = inputs.iter()
+            .filter(|i| matches!(i, FastqInput::Pair(..)))
+            .count();
+        let missing = inputs.iter()
+            .filter(|i| matches!(i, FastqInput::Single(_, PairStatus::MissingMate)))
+            .count();
+        let single = inputs.iter()
+            .filter(|i| matches!(i, FastqInput::Single(_, PairStatus::SingleEnd)))
+            .count();
+
+        assert_eq!(1, pairs);
+        assert_eq!(1, missing);
+        assert_eq!(1, single);
+    }
+}
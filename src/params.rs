@@ -0,0 +1,52 @@
+//! Heru Handika
+//! User options from the command line.
+
+use crate::adapters::{self, Adapter};
+use crate::qscores::QEncoding;
+use crate::stats::LengthBins;
+
+pub struct QcParams {
+    pub iscsv: bool,
+    /// Sample name for standard input.
+    pub name: Option<String>,
+    /// Q-score encoding. Detected from the reads if None.
+    pub encoding: Option<QEncoding>,
+    /// Bins of the read length histogram.
+    pub length_bins: LengthBins,
+    /// Bases below this q-score are low quality.
+    pub low_q: u32,
+    /// Reads from the start of each file used to estimate duplication.
+    pub dup_sample: usize,
+    /// Sequences above this percentage of reads are overrepresented.
+    pub overrep_min: f64,
+    /// Adapters searched in the reads.
+    pub adapters: Vec<Adapter>,
+    /// K-mer size of the k-mer content.
+    pub kmer_size: usize,
+    /// Shortest poly-G, poly-A, or poly-T tail.
+    pub min_tail: usize,
+}
+
+impl Default for QcParams {
+    fn default() -> Self {
+        Self {
+            iscsv: false,
+            name: None,
+            encoding: None,
+            length_bins: LengthBins::default(),
+            low_q: 20,
+            dup_sample: 1_000_000,
+            overrep_min: 0.1,
+            adapters: adapters::default_adapters(),
+            kmer_size: 7,
+            min_tail: 10,
+        }
+    }
+}
+
+impl QcParams {
+    /// Whether the record (from 1) is used to estimate duplication.
+    pub fn in_dup_sample(&self, record: usize) -> bool {
+        record <= self.dup_sample
+    }
+}
@@ -1,57 +1,311 @@
 //! Heru Handika
-//! Only support Illumina 1.8 Quality Scores
+//! Quality scores. Supports Phred+33 (Sanger, Illumina 1.8+),
+//! Phred+64 (Illumina 1.3-1.7), and Solexa+64 encodings.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::error::QcError;
+
+// Highest character of Illumina 1.8+ reads (Q42).
+// Phred+64 and Solexa reads go above it.
+const PHRED33_ILLUMINA_MAX: u8 = b'K';
+// Highest character of Phred+64 and Solexa reads (Q41).
+// Phred+33 long reads (PacBio HiFi) go above it.
+const LEGACY_MAX: u8 = b'i';
+const MAX_QCHAR: u8 = b'~';
+// Phred scores from 0 to 93.
+const QBINS: usize = 94;
+// Upper bounds of the expected error bins. 
+// The last bin holds everything above.
+const EE_BINS: [f64; 6] = [0.5, 1.0, 2.0, 3.0, 5.0, 10.0];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QEncoding {
+    Phred33,
+    Phred64,
+    Solexa,
+}
+
+impl QEncoding {
+    /// Guesses the encoding from the lowest and
+    /// the highest quality characters in the reads.
+    /// Phred+64 and Solexa are picked only if the whole
+    /// range fits the encoding. Otherwise, Phred+33.
+    pub fn detect(min: u8, max: u8) -> Self {
+        if max <= PHRED33_ILLUMINA_MAX || max > LEGACY_MAX {
+            return QEncoding::Phred33;
+        }
+
+        match min {
+            m if m >= b'@' => QEncoding::Phred64,
+            m if m >= b';' => QEncoding::Solexa,
+            _ => QEncoding::Phred33,
+        }
+    }
+
+    /// Phred score of a quality character.
+    /// None if the character is out of range.
+    #[inline(always)]
+    pub fn decode(&self, scr: u8) -> Option<u32> {
+        match self {
+            QEncoding::Phred33 if (b'!'..=MAX_QCHAR).contains(&scr) =>
+                Some((scr - b'!') as u32),
+            QEncoding::Phred64 if (b'@'..=MAX_QCHAR).contains(&scr) =>
+                Some((scr - b'@') as u32),
+            QEncoding::Solexa if (b';'..=MAX_QCHAR).contains(&scr) =>
+                Some(solexa_to_phred(scr as i32 - 64)),
+            _ => None,
+        }
+    }
+}
+
+// Solexa scores are log-odds. Rounded to the nearest Phred score.
+fn solexa_to_phred(q: i32) -> u32 {
+    let phred = 10.0 * (10f64.powf(q as f64 / 10.0) + 1.0).log10();
+    phred.round() as u32
+}
+
+impl fmt::Display for QEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QEncoding::Phred33 => write!(f, "Phred+33"),
+            QEncoding::Phred64 => write!(f, "Phred+64"),
+            QEncoding::Solexa => write!(f, "Solexa+64"),
+        }
+    }
+}
+
+impl FromStr for QEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "phred33" => Ok(QEncoding::Phred33),
+            "phred64" => Ok(QEncoding::Phred64),
+            "solexa" => Ok(QEncoding::Solexa),
+            _ => Err(format!("Unknown q-score encoding {}", s)),
+        }
+    }
+}
 
 pub struct QScore {
     pub q_len: u32,
     pub mean_q: f64,
     pub low_bases: u32,
+    pub q20_bases: u32,
+    pub q30_bases: u32,
     pub sum: u32,
+    /// Sum of the base error probabilities.
+    pub expected_errors: f64,
+    /// Mean q-score from the mean error probability.
+    pub error_mean_q: f64,
 }
 
 impl QScore {
-    pub fn analyze_qscores(q_line: &[u8]) -> Self {
-        let q_scores = q_line.iter()
-            .map(|scr| 
-                    { if *scr < 75 {
-                        *scr as u32 - 33
-                    } else {
-                        panic!("UNSUPPORTED Q-SCORE ENCODING!");
-                    }
-                })
-            .collect::<Vec<u32>>(); 
-
+    /// Decodes the quality line into phred and summarizes it.
+    /// Bases below low_q are counted as low-quality bases.
+    pub fn analyze_qscores(q_line: &[u8], 
+                           encoding: QEncoding, 
+                           low_q: u32,
+                           phred: &mut Vec<u8>
+        ) -> Result<Self, QcError> {
         let mut q = Self {
-                q_len: q_scores.iter().count() as u32,
-                low_bases: q_scores.iter()
-                    .filter(|&x| *x < 20)
-                    .count() as u32,
-                sum: q_scores.iter().sum(),
-                mean_q: 0.0
+                q_len: q_line.len() as u32,
+                low_bases: 0,
+                q20_bases: 0,
+                q30_bases: 0,
+                sum: 0,
+                mean_q: 0.0,
+                expected_errors: 0.0,
+                error_mean_q: 0.0,
             };
 
+        phred.clear();
+
+        for scr in q_line {
+            let q_score = encoding.decode(*scr)
+                .ok_or(QcError::UnsupportedQScore(*scr))?;
+
+            if q_score < low_q {
+                q.low_bases += 1;
+            }
+            if q_score >= 20 {
+                q.q20_bases += 1;
+            }
+            if q_score >= 30 {
+                q.q30_bases += 1;
+            }
+            q.sum += q_score;
+            q.expected_errors += error_prob(q_score);
+            phred.push(q_score as u8);
+        }
+
         q.mean();
+        q.error_mean();
 
-        q
+        Ok(q)
     }
 
     fn mean(&mut self) {
         self.mean_q = self.sum as f64 / self.q_len as f64
     }
+
+    fn error_mean(&mut self) {
+        self.error_mean_q = error_to_qscore(self.expected_errors / self.q_len as f64);
+    }
+}
+
+// Error probability of a phred score.
+fn error_prob(q: u32) -> f64 {
+    static PROBS: OnceLock<[f64; QBINS]> = OnceLock::new();
+    let probs = PROBS.get_or_init(|| {
+        let mut probs = [0.0; QBINS];
+        probs.iter_mut()
+            .enumerate()
+            .for_each(|(q, prob)| *prob = 10f64.powf(-(q as f64) / 10.0));
+        probs
+    });
+
+    probs[q as usize]
+}
+
+/// Phred score of an error probability.
+pub fn error_to_qscore(prob: f64) -> f64 {
+    -10.0 * prob.log10()
+}
+
+/// Per-read mean q-scores in bins of 1. Counts both 
+/// the arithmetic and the error-based means.
+pub struct ReadQualityDist {
+    counts: [u64; QBINS],
+    error_counts: [u64; QBINS],
+}
+
+impl Default for ReadQualityDist {
+    fn default() -> Self {
+        Self { 
+            counts: [0; QBINS],
+            error_counts: [0; QBINS],
+        }
+    }
 }
 
+impl ReadQualityDist {
+    /// Adds the mean q-scores of a read. 
+    /// Reads without q-scores are skipped.
+    pub fn add(&mut self, qscores: &QScore) {
+        if qscores.q_len > 0 {
+            self.counts[qscores.mean_q as usize] += 1;
+            self.error_counts[qscores.error_mean_q as usize] += 1;
+        }
+    }
 
+    pub fn merge(&mut self, other: &ReadQualityDist) {
+        self.counts.iter_mut()
+            .zip(other.counts.iter())
+            .for_each(|(count, other)| *count += other);
+        self.error_counts.iter_mut()
+            .zip(other.error_counts.iter())
+            .for_each(|(count, other)| *count += other);
+    }
+
+    /// Read counts from the lowest to the highest mean q-score.
+    pub fn histogram(&self) -> Vec<QualityBin> {
+        let observed = |q: &usize| self.counts[*q] > 0 || self.error_counts[*q] > 0;
+        let first = (0..QBINS).find(observed);
+        let last = (0..QBINS).rev().find(observed);
+
+        match (first, last) {
+            (Some(first), Some(last)) => (first..=last)
+                .map(|q| QualityBin { 
+                    q: q as u32, 
+                    count: self.counts[q],
+                    error_count: self.error_counts[q],
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Number of reads with a mean q-score from q to below q + 1.
+pub struct QualityBin {
+    pub q: u32,
+    pub count: u64,
+    /// Reads by the error-based mean q-score.
+    pub error_count: u64,
+}
+
+/// Per-read expected errors, as used by maxEE read filters.
+#[derive(Default)]
+pub struct ExpectedErrorDist {
+    counts: [u64; EE_BINS.len() + 1],
+}
+
+impl ExpectedErrorDist {
+    pub fn add(&mut self, qscores: &QScore) {
+        let bin = EE_BINS.iter()
+            .position(|max| qscores.expected_errors <= *max)
+            .unwrap_or(EE_BINS.len());
+        self.counts[bin] += 1;
+    }
+
+    pub fn merge(&mut self, other: &ExpectedErrorDist) {
+        self.counts.iter_mut()
+            .zip(other.counts.iter())
+            .for_each(|(count, other)| *count += other);
+    }
+
+    pub fn histogram(&self) -> Vec<ErrorBin> {
+        let total: u64 = self.counts.iter().sum();
+        let mut cumsum = 0;
+
+        self.counts.iter()
+            .enumerate()
+            .map(|(idx, count)| {
+                cumsum += count;
+                ErrorBin {
+                    min: if idx == 0 { 0.0 } else { EE_BINS[idx - 1] },
+                    max: EE_BINS.get(idx).copied(),
+                    count: *count,
+                    cumulative: cumsum as f64 / total as f64,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Number of reads with expected errors above min, up to max.
+/// No max for the last bin.
+pub struct ErrorBin {
+    pub min: f64,
+    pub max: Option<f64>,
+    pub count: u64,
+    /// Fraction of reads with at most max expected errors.
+    pub cumulative: f64,
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use assert_approx_eq::assert_approx_eq;
 
     #[test]
     fn qscore_test () {
         let p = String::from("II!)");
         let q = String::from("II");
 
-        let q_score = QScore::analyze_qscores(q.as_bytes());
-        let p_score = QScore::analyze_qscores(p.as_bytes());
+        let mut phred = Vec::new();
+
+        let q_score = QScore::analyze_qscores(q.as_bytes(), QEncoding::Phred33, 20, &mut phred)
+            .unwrap();
+        assert_eq!(vec![40, 40], phred);
+
+        let p_score = QScore::analyze_qscores(p.as_bytes(), QEncoding::Phred33, 20, &mut phred)
+            .unwrap();
+        assert_eq!(vec![40, 40, 0, 8], phred);
 
         assert_eq!(2, q_score.q_len);
         assert_eq!(40.0, q_score.mean_q);
@@ -62,7 +316,8 @@ mod tests {
     #[test]
     fn decode_qscores_test() {
         let q = String::from("II");
-        let qs = QScore::analyze_qscores(q.as_bytes());
+        let qs = QScore::analyze_qscores(q.as_bytes(), QEncoding::Phred33, 20, &mut Vec::new())
+            .unwrap();
 
         // let res = vec![40, 40];
 
@@ -70,10 +325,98 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "UNSUPPORTED Q-SCORE ENCODING!")]
-    fn decode_panic_qscore() {
-        let p = String::from("II!)K");
+    fn decode_error_qscore() {
+        let p = String::from("II! )");
+        let res = QScore::analyze_qscores(p.as_bytes(), QEncoding::Phred33, 20, &mut Vec::new());
+
+        assert!(matches!(res, Err(QcError::UnsupportedQScore(b' '))));
+    }
+
+    #[test]
+    fn decode_high_qscore_test() {
+        let qs = QScore::analyze_qscores(b"~K", QEncoding::Phred33, 20, &mut Vec::new()).unwrap();
+
+        assert_eq!(93 + 42, qs.sum);
+    }
 
-        QScore::analyze_qscores(&p.as_bytes());
+    #[test]
+    fn decode_legacy_qscore_test() {
+        let phred64 = QScore::analyze_qscores(b"hB", QEncoding::Phred64, 20, &mut Vec::new()).unwrap();
+        let solexa = QScore::analyze_qscores(b";@h", QEncoding::Solexa, 20, &mut Vec::new()).unwrap();
+
+        assert_eq!(40 + 2, phred64.sum);
+        assert_eq!(1, phred64.low_bases);
+        assert_eq!(1 + 3 + 40, solexa.sum);
+        assert!(QScore::analyze_qscores(b"5", QEncoding::Phred64, 20, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn q20_q30_test() {
+        let qs = QScore::analyze_qscores(b"+5?I", QEncoding::Phred33, 25, &mut Vec::new())
+            .unwrap();
+
+        assert_eq!(2, qs.low_bases);
+        assert_eq!(3, qs.q20_bases);
+        assert_eq!(2, qs.q30_bases);
+    }
+
+    #[test]
+    fn read_quality_dist_test() {
+        let mut dist = ReadQualityDist::default();
+        let mut other = ReadQualityDist::default();
+        let mut phred = Vec::new();
+        [&b"II"[..], b"I5", b"5I", b""].iter()
+            .for_each(|q| {
+                dist.add(&QScore::analyze_qscores(q, QEncoding::Phred33, 20, &mut phred).unwrap())
+            });
+        other.add(&QScore::analyze_qscores(b"++", QEncoding::Phred33, 20, &mut phred).unwrap());
+        dist.merge(&other);
+
+        let hist = dist.histogram();
+
+        assert_eq!(31, hist.len());
+        assert_eq!(10, hist[0].q);
+        assert_eq!(1, hist[0].count);
+        assert_eq!(2, hist[20].count);
+        assert_eq!(1, hist[30].count);
+        assert!(ReadQualityDist::default().histogram().is_empty());
+    }
+
+    #[test]
+    fn expected_errors_test() {
+        let qs = QScore::analyze_qscores(b"5+", QEncoding::Phred33, 20, &mut Vec::new())
+            .unwrap();
+
+        assert_approx_eq!(0.11, qs.expected_errors, 1e-9);
+        assert_approx_eq!(12.596373, qs.error_mean_q, 1e-6);
+        assert!(qs.error_mean_q < qs.mean_q);
+
+        let mut dist = ExpectedErrorDist::default();
+        let mut other = ExpectedErrorDist::default();
+        dist.add(&qs);
+        dist.add(&QScore::analyze_qscores(b"!!", QEncoding::Phred33, 20, &mut Vec::new()).unwrap());
+        other.add(&QScore::analyze_qscores(b"5", QEncoding::Phred33, 20, &mut Vec::new()).unwrap());
+        dist.merge(&other);
+
+        let hist = dist.histogram();
+
+        assert_eq!(7, hist.len());
+        assert_eq!(2, hist[0].count);
+        assert_eq!(1, hist[2].count);
+        assert_eq!(Some(2.0), hist[2].max);
+        assert_eq!(1.0, hist[2].cumulative);
+        assert_eq!(None, hist[6].max);
+    }
+
+    #[test]
+    fn detect_encoding_test() {
+        assert_eq!(QEncoding::Phred33, QEncoding::detect(b'#', b'J'));
+        assert_eq!(QEncoding::Phred33, QEncoding::detect(b'A', b'I'));
+        assert_eq!(QEncoding::Phred33, QEncoding::detect(b'!', b'~'));
+        assert_eq!(QEncoding::Phred64, QEncoding::detect(b'B', b'h'));
+        assert_eq!(QEncoding::Solexa, QEncoding::detect(b';', b'h'));
+        assert_eq!(QEncoding::Phred33, QEncoding::detect(b'?', b'~'));
+        assert_eq!(QEncoding::Phred33, QEncoding::detect(b'@', b'j'));
+        assert_eq!(QEncoding::Phred64, QEncoding::detect(b'@', b'i'));
     }
 }
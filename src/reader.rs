@@ -0,0 +1,253 @@
+//! Heru Handika
+//! Module to open sequence files, named pipes, and standard input.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use bzip2::bufread::MultiBzDecoder;
+use flate2::bufread::MultiGzDecoder;
+use xz2::bufread::XzDecoder;
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+use crate::bgzf::BgzfReader;
+use crate::error::QcError;
+
+/// Input name for standard input.
+pub const STDIN: &str = "-";
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const BZIP2_MAGIC: [u8; 3] = [b'B', b'Z', b'h'];
+const XZ_MAGIC: [u8; 6] = [0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZIP_MAGIC: [u8; 4] = [0x50, 0x4b, 0x03, 0x04];
+// Bytes needed to tell the formats apart. BGZF needs the most.
+const MAGIC_LEN: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Bgzf,
+    Zstd,
+    Bzip2,
+    Xz,
+    Plain,
+}
+
+impl Compression {
+    /// Detects the compression from the first bytes of a stream.
+    /// Returns None for formats we can't read.
+    pub fn from_magic(magic: &[u8]) -> Option<Self> {
+        match magic {
+            m if m.starts_with(&GZIP_MAGIC) && is_bgzf(m) => Some(Compression::Bgzf),
+            m if m.starts_with(&GZIP_MAGIC) => Some(Compression::Gzip),
+            m if m.starts_with(&ZSTD_MAGIC) => Some(Compression::Zstd),
+            m if m.starts_with(&BZIP2_MAGIC) => Some(Compression::Bzip2),
+            m if m.starts_with(&XZ_MAGIC) => Some(Compression::Xz),
+            m if m.starts_with(&ZIP_MAGIC) => None,
+            _ => Some(Compression::Plain),
+        }
+    }
+}
+
+// BGZF is gzip with a 'BC' extra subfield holding the block size.
+fn is_bgzf(magic: &[u8]) -> bool {
+    magic.len() >= 16 
+        && magic[3] & 0x04 != 0 
+        && magic[12..14] == [b'B', b'C']
+}
+
+pub fn is_stdin(input: &Path) -> bool {
+    input == Path::new(STDIN)
+}
+
+/// File name of the input. Standard input is named "stdin".
+pub fn sample_name(input: &Path) -> String {
+    if is_stdin(input) {
+        return String::from("stdin");
+    }
+
+    input.file_name()
+        .map(|f| f.to_string_lossy().into_owned())
+        .unwrap_or_else(|| input.to_string_lossy().into_owned())
+}
+
+/// Opens a file, a named pipe, or standard input ("-").
+/// Compression is detected from the first bytes of the stream,
+/// not from the file extension.
+pub fn open_input(input: &Path) -> Result<Box<dyn BufRead + Send>, QcError> {
+    open_reader(open_raw(input)?, input)
+}
+
+fn open_reader<R: Read + Send + 'static>(mut raw: R, 
+                                          input: &Path
+    ) -> Result<Box<dyn BufRead + Send>, QcError> {
+    let magic = read_magic(&mut raw)?;
+    let compression = Compression::from_magic(&magic)
+        .ok_or_else(|| QcError::UnsupportedFormat(input.to_path_buf()))?;
+    let buff = BufReader::new(io::Cursor::new(magic).chain(raw));
+
+    let reader: Box<dyn BufRead + Send> = match compression {
+        Compression::Bgzf => Box::new(BgzfReader::new(buff)),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(buff))),
+        Compression::Zstd => Box::new(BufReader::new(ZstdDecoder::with_buffer(buff)?)),
+        Compression::Bzip2 => Box::new(BufReader::new(MultiBzDecoder::new(buff))),
+        Compression::Xz => Box::new(BufReader::new(XzDecoder::new_multi_decoder(buff))),
+        Compression::Plain => Box::new(buff),
+    };
+
+    Ok(reader)
+}
+
+/// Compression of a file from its first bytes.
+pub fn detect_compression(input: &Path) -> Result<Compression, QcError> {
+    let magic = read_magic(&mut open_raw(input)?)?;
+    Compression::from_magic(&magic)
+        .ok_or_else(|| QcError::UnsupportedFormat(input.to_path_buf()))
+}
+
+// Pipes may return a few bytes per read, so reads 
+// until MAGIC_LEN bytes or the end of the stream.
+fn read_magic<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut magic = Vec::with_capacity(MAGIC_LEN);
+    reader.take(MAGIC_LEN as u64).read_to_end(&mut magic)?;
+    Ok(magic)
+}
+
+/// Checks the sequence extension of a file. For compressed files, 
+/// the last extension is skipped (reads.fastq.zst, reads.fq.bz2).
+pub fn has_seq_extension(input: &Path, extensions: &[&str]) -> bool {
+    let is_match = |path: &Path| {
+        path.extension()
+            .is_some_and(|ext| extensions.iter().any(|e| ext == *e))
+    };
+
+    if is_match(input) {
+        return true;
+    }
+
+    match input.file_stem() {
+        Some(stem) if is_match(Path::new(stem)) => 
+            detect_compression(input).is_ok_and(|c| c != Compression::Plain),
+        _ => false,
+    }
+}
+
+fn open_raw(input: &Path) -> Result<Box<dyn Read + Send>, QcError> {
+    if is_stdin(input) {
+        Ok(Box::new(io::stdin()))
+    } else {
+        Ok(Box::new(File::open(input)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn open_gzip_input_test() {
+        let input = PathBuf::from("test_files/contigs.fasta.gz");
+        let mut buff = open_input(&input).unwrap();
+        let mut line = String::new();
+        buff.read_line(&mut line).unwrap();
+
+        assert_eq!(">contigs 1\n", line);
+    }
+
+    #[test]
+    fn open_plain_input_test() {
+        let input = PathBuf::from("test_files/contigs.fasta");
+        let mut buff = open_input(&input).unwrap();
+        let mut line = String::new();
+        buff.read_line(&mut line).unwrap();
+
+        assert_eq!(">contigs 1\n", line);
+    }
+
+    #[test]
+    fn open_compressed_input_test() {
+        let inputs = [
+            "test_files/contigs.fasta.bz2",
+            "test_files/contigs.fasta.xz",
+            "test_files/contigs.fasta.zst",
+        ];
+
+        inputs.iter()
+            .for_each(|input| {
+                let mut buff = open_input(Path::new(input)).unwrap();
+                let mut line = String::new();
+                buff.read_line(&mut line).unwrap();
+
+                assert_eq!(">contigs 1\n", line);
+            });
+    }
+
+    #[test]
+    fn detect_bgzf_test() {
+        let bgzf = detect_compression(Path::new("test_files/valid_bgzf.fastq.gz"));
+        let gzip = detect_compression(Path::new("test_files/valid.fastq.gz"));
+
+        assert_eq!(Compression::Bgzf, bgzf.unwrap());
+        assert_eq!(Compression::Gzip, gzip.unwrap());
+    }
+
+    // Returns one byte per read, like a slow pipe.
+    struct ByteReader(io::Cursor<Vec<u8>>);
+
+    impl Read for ByteReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn open_slow_reader_test() {
+        let input = PathBuf::from("test_files/valid_bgzf.fastq.gz");
+        let bytes = std::fs::read(&input).unwrap();
+        let mut expected = String::new();
+        open_input(&input).unwrap().read_line(&mut expected).unwrap();
+
+        let mut buff = open_reader(ByteReader(io::Cursor::new(bytes)), &input).unwrap();
+        let mut line = String::new();
+        buff.read_line(&mut line).unwrap();
+
+        let mut short = ByteReader(io::Cursor::new(vec![0x1f, 0x8b]));
+
+        assert!(line.starts_with('@'));
+        assert_eq!(expected, line);
+        assert_eq!(vec![0x1f, 0x8b], read_magic(&mut short).unwrap());
+    }
+
+    #[test]
+    fn compression_magic_test() {
+        let mut bgzf = vec![0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff, 6, 0];
+        bgzf.extend_from_slice(&[b'B', b'C', 2, 0, 0x1b, 0]);
+
+        assert_eq!(Some(Compression::Bgzf), Compression::from_magic(&bgzf));
+        assert_eq!(Some(Compression::Gzip), Compression::from_magic(&[0x1f, 0x8b, 0x08, 0]));
+        assert_eq!(Some(Compression::Bzip2), Compression::from_magic(b"BZh91AY"));
+        assert_eq!(Some(Compression::Plain), Compression::from_magic(b"@read1"));
+        assert_eq!(None, Compression::from_magic(&ZIP_MAGIC));
+    }
+
+    #[test]
+    fn has_seq_extension_test() {
+        let fasta = ["fasta", "fas", "fa"];
+
+        assert!(has_seq_extension(Path::new("test_files/contigs.fasta.zst"), &fasta));
+        assert!(has_seq_extension(Path::new("test_files/contigs.fasta.bz2"), &fasta));
+        assert!(!has_seq_extension(Path::new("test_files/contigs.fasta.zip"), &fasta));
+        assert!(!has_seq_extension(Path::new("test_files/valid.fastq.gz"), &fasta));
+    }
+
+    #[test]
+    fn is_stdin_test() {
+        assert!(is_stdin(Path::new("-")));
+        assert!(!is_stdin(Path::new("test_files/contigs.fasta")));
+        assert_eq!("stdin", sample_name(Path::new("-")));
+        assert_eq!("contigs.fasta", sample_name(Path::new("test_files/contigs.fasta")));
+    }
+}
@@ -0,0 +1,113 @@
+//! Heru Handika
+//! Instruments, runs, flowcells, and lanes in a file,
+//! from Casava 1.8 read IDs. Files concatenated from
+//! several runs otherwise look like a single sample.
+
+use crate::header::IlluminaId;
+
+/// Reads from a flowcell lane of a sequencing run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunLane {
+    pub instrument: String,
+    pub run: String,
+    pub flowcell: String,
+    pub lane: u32,
+    pub reads: u64,
+}
+
+impl RunLane {
+    fn new(id: &IlluminaId, reads: u64) -> Self {
+        Self {
+            instrument: String::from_utf8_lossy(id.instrument).into_owned(),
+            run: String::from_utf8_lossy(id.run).into_owned(),
+            flowcell: String::from_utf8_lossy(id.flowcell).into_owned(),
+            lane: id.lane,
+            reads,
+        }
+    }
+
+    fn matches(&self, id: &IlluminaId) -> bool {
+        self.lane == id.lane
+            && self.flowcell.as_bytes() == id.flowcell
+            && self.run.as_bytes() == id.run
+            && self.instrument.as_bytes() == id.instrument
+    }
+
+    /// Whether both lanes are from the same run and flowcell.
+    pub fn same_run(&self, other: &RunLane) -> bool {
+        self.instrument == other.instrument
+            && self.run == other.run
+            && self.flowcell == other.flowcell
+    }
+}
+
+/// Distinct run lanes in the order they are first seen.
+/// Files rarely have more than a few, so they are
+/// searched in a list.
+#[derive(Default)]
+pub struct RunLanes {
+    lanes: Vec<RunLane>,
+}
+
+impl RunLanes {
+    pub fn add(&mut self, id: &IlluminaId) {
+        match self.lanes.iter_mut().find(|lane| lane.matches(id)) {
+            Some(lane) => lane.reads += 1,
+            None => self.lanes.push(RunLane::new(id, 1)),
+        }
+    }
+
+    pub fn merge(&mut self, other: &RunLanes) {
+        other.lanes.iter()
+            .for_each(|other| {
+                match self.lanes.iter_mut().find(|lane| lane.same_run(other) && lane.lane == other.lane) {
+                    Some(lane) => lane.reads += other.reads,
+                    None => self.lanes.push(other.clone()),
+                }
+            });
+    }
+
+    pub fn lanes(&self) -> Vec<RunLane> {
+        self.lanes.clone()
+    }
+}
+
+/// Number of distinct instrument, run, and flowcell
+/// combinations. Lanes of the same run count once.
+pub fn count_runs(lanes: &[RunLane]) -> usize {
+    lanes.iter()
+        .enumerate()
+        .filter(|(idx, lane)| !lanes[..*idx].iter().any(|prev| prev.same_run(lane)))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header;
+
+    #[test]
+    fn run_lanes_test() {
+        let mut lanes = RunLanes::default();
+        let mut other = RunLanes::default();
+        let lane_1 = header::illumina_id(b"@A00:7:FC1:1:1101:10:20").unwrap();
+        let lane_2 = header::illumina_id(b"@A00:7:FC1:2:1101:10:20").unwrap();
+        let other_run = header::illumina_id(b"@A00:9:FC2:1:1101:10:20").unwrap();
+        lanes.add(&lane_1);
+        lanes.add(&lane_1);
+        other.add(&lane_2);
+        other.add(&lane_1);
+        lanes.merge(&other);
+
+        let res = lanes.lanes();
+
+        assert_eq!(2, res.len());
+        assert_eq!(("A00", "7", "FC1"), (res[0].instrument.as_str(), res[0].run.as_str(), res[0].flowcell.as_str()));
+        assert_eq!((1, 3), (res[0].lane, res[0].reads));
+        assert_eq!((2, 1), (res[1].lane, res[1].reads));
+        assert_eq!(1, count_runs(&res));
+
+        lanes.add(&other_run);
+        assert_eq!(2, count_runs(&lanes.lanes()));
+    }
+}
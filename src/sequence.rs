@@ -1,21 +1,47 @@
 //! Heru Handika
 //! Modules to process sequencing data
 
-use std::path::PathBuf;
-
-use crate::qscores::QScore;
-use crate::stats::{self, NStats};
+use std::path::Path;
+
+use crate::adapters::{AdapterContent, AdapterProfile};
+use crate::cycles::{BaseComposition, CycleBases, CycleQuality, CycleSummary};
+use crate::duplication::{DupCounter, DupLevel, Overrepresented};
+use crate::header::{CasavaComment, IlluminaId};
+use crate::indexes::{IndexCounts, IndexSummary};
+use crate::kmers::{EnrichedKmer, KmerCounter};
+use crate::pairs::PairStatus;
+use crate::params::QcParams;
+use crate::qscores::{self, ErrorBin, ExpectedErrorDist, QEncoding, QScore, QualityBin, ReadQualityDist};
+use crate::reader;
+use crate::runs::{self, RunLane, RunLanes};
+use crate::stats::{GcBin, GcDist, LengthBin, LengthDist, NStats, OnlineVariance};
+use crate::tails::{PolyTail, TailCounts};
+use crate::tiles::{LaneSummary, TileQuality, TileSummary};
+
+/// GC deviation above which the GC distribution is flagged.
+/// Contamination and adapter dimers show up as a second mode.
+pub const GC_DEVIATION_WARN: f64 = 15.0;
+/// Percentage of reads with an adapter above which
+/// the reads should be trimmed.
+pub const ADAPTER_WARN: f64 = 5.0;
+
+/// Records per batch when a file is split across threads. 
+/// Batches end at fixed records, so the results
+/// do not depend on the number of threads.
+pub const BATCH_SIZE: usize = 4096;
+/// Batches also end once they hold this many bytes.
+pub const BATCH_BYTES: usize = 1 << 22;
 
 pub struct SeqReads {
-    pub seq_len: u32,
-    pub gc_count: u32,
-    pub n_count: u32,
+    pub seq_len: u64,
+    pub gc_count: u64,
+    pub n_count: u64,
 }
 
 impl SeqReads {
     pub fn get_seq_stats(reads: &[u8]) -> Self {
         let mut seq = Self {
-            seq_len: reads.iter().count() as u32,
+            seq_len: reads.len() as u64,
             gc_count: 0,
             n_count: 0,
         };
@@ -36,66 +62,324 @@ impl SeqReads {
     
 }
 
+/// A read with its decoded q-scores and stats. Computed once
+/// and added to the file and the mate accumulators.
+pub struct ReadInfo<'a> {
+    pub seq: &'a [u8],
+    pub phred: &'a [u8],
+    pub reads: SeqReads,
+    pub qscores: QScore,
+    /// Sequence hash. None if the read is not in the duplication sample.
+    pub dup_hash: Option<u64>,
+    /// First match of each adapter in the read.
+    pub adapter_hits: &'a [Option<usize>],
+    /// K-mer code at each read position.
+    pub kmers: &'a [Option<u32>],
+    pub tail: Option<PolyTail>,
+    /// Casava 1.8 read ID. None for other read names.
+    pub id: Option<IlluminaId<'a>>,
+    /// Casava 1.8 comment. None for other comments.
+    pub comment: Option<CasavaComment<'a>>,
+}
+
+/// Running totals of FASTQ reads. Memory stays 
+/// the same regardless of the number of reads.
+/// Batches of reads can be counted separately and merged.
+#[derive(Default)]
+pub struct ReadAccumulator {
+    read_count: u64,
+    total_bp: u64,
+    total_gc: u64,
+    total_n: u64,
+    sum_qlen: u64,
+    sum_low_bases: u64,
+    sum_q20_bases: u64,
+    sum_q30_bases: u64,
+    sum_qscores: f64,
+    sum_expected_errors: f64,
+    read_quality: ReadQualityDist,
+    expected_errors: ExpectedErrorDist,
+    seq_len: LengthDist,
+    seq_var: OnlineVariance,
+    cycle_quality: CycleQuality,
+    cycle_bases: CycleBases,
+    gc_dist: GcDist,
+    duplication: DupCounter,
+    adapter_content: AdapterContent,
+    kmers: KmerCounter,
+    tails: TailCounts,
+    tiles: TileQuality,
+    indexes: IndexCounts,
+    runs: RunLanes,
+}
+
+impl ReadAccumulator {
+    pub fn add_read(&mut self, read: &ReadInfo) {
+        let reads = &read.reads;
+        let qscores = &read.qscores;
+
+        self.read_count += 1;
+        self.total_bp += reads.seq_len;
+        self.total_gc += reads.gc_count;
+        self.total_n += reads.n_count;
+        self.sum_qlen += qscores.q_len as u64;
+        self.sum_low_bases += qscores.low_bases as u64;
+        self.sum_q20_bases += qscores.q20_bases as u64;
+        self.sum_q30_bases += qscores.q30_bases as u64;
+        self.sum_qscores += qscores.mean_q;
+        self.sum_expected_errors += qscores.expected_errors;
+        self.read_quality.add(qscores);
+        self.expected_errors.add(qscores);
+        self.seq_len.add(reads.seq_len);
+        self.seq_var.add(reads.seq_len as f64);
+        self.cycle_quality.add(read.phred);
+        self.cycle_bases.add(read.seq);
+        self.gc_dist.add(reads.gc_count, reads.seq_len - reads.n_count);
+        self.adapter_content.add(read.adapter_hits);
+        self.kmers.add(read.kmers);
+        self.tails.add(read.tail.as_ref());
+        if let Some(id) = &read.id {
+            self.tiles.add(id, qscores.mean_q);
+            self.runs.add(id);
+        }
+        if let Some(comment) = &read.comment {
+            self.indexes.add(comment);
+        }
+        if let Some(hash) = read.dup_hash {
+            self.duplication.add(hash, read.seq);
+        }
+    }
+
+    /// Adds the totals of a batch processed separately.
+    pub fn merge(&mut self, other: &ReadAccumulator) {
+        self.read_count += other.read_count;
+        self.total_bp += other.total_bp;
+        self.total_gc += other.total_gc;
+        self.total_n += other.total_n;
+        self.sum_qlen += other.sum_qlen;
+        self.sum_low_bases += other.sum_low_bases;
+        self.sum_q20_bases += other.sum_q20_bases;
+        self.sum_q30_bases += other.sum_q30_bases;
+        self.sum_qscores += other.sum_qscores;
+        self.sum_expected_errors += other.sum_expected_errors;
+        self.read_quality.merge(&other.read_quality);
+        self.expected_errors.merge(&other.expected_errors);
+        self.seq_len.merge(&other.seq_len);
+        self.seq_var.merge(&other.seq_var);
+        self.cycle_quality.merge(&other.cycle_quality);
+        self.cycle_bases.merge(&other.cycle_bases);
+        self.gc_dist.merge(&other.gc_dist);
+        self.duplication.merge(&other.duplication);
+        self.adapter_content.merge(&other.adapter_content);
+        self.kmers.merge(&other.kmers);
+        self.tails.merge(&other.tails);
+        self.tiles.merge(&other.tiles);
+        self.indexes.merge(&other.indexes);
+        self.runs.merge(&other.runs);
+    }
+}
+
 pub struct FastqStats {
     pub path: String,
     pub seqname: String,
-    pub read_count: u32,
+    pub read_count: u64,
     pub total_bp: u64, 
-    pub min_reads: u32,
-    pub max_reads: u32,
+    pub min_reads: u64,
+    pub max_reads: u64,
     pub mean_reads: f64,
     pub median_reads: f64,
     pub sd_reads: f64,
-    pub total_gc: u32,
+    pub total_gc: u64,
     pub gc_content: f64,
-    pub total_n: u32,
+    /// GC-content without poly-G, poly-A, and poly-T tails.
+    pub gc_content_no_tails: f64,
+    pub poly_g_reads: u64,
+    pub poly_a_reads: u64,
+    pub poly_t_reads: u64,
+    pub mean_tail_len: f64,
+    pub total_n: u64,
     pub n_content: f64,
     pub sum_qlen: u64,
     pub mean_qscores: f64,
-    pub sum_low_bases: u32,
+    /// Mean q-score from the mean base error probability.
+    pub error_mean_qscores: f64,
+    pub mean_expected_errors: f64,
+    pub ee_hist: Vec<ErrorBin>,
+    pub sum_low_bases: u64,
     pub low_bases_ratio: f64,
+    /// Q-score threshold of the low-quality bases.
+    pub low_q: u32,
+    pub q20_ratio: f64,
+    pub q30_ratio: f64,
+    pub mean_q_hist: Vec<QualityBin>,
+    pub mate: Option<String>,
+    pub pair_status: PairStatus,
+    pub mates: Vec<FastqStats>,
+    pub encoding: QEncoding,
+    pub cycle_quality: Vec<CycleSummary>,
+    pub base_content: Vec<BaseComposition>,
+    pub length_hist: Vec<LengthBin>,
+    pub gc_hist: Vec<GcBin>,
+    /// Percentage of reads deviating from a normal GC distribution.
+    pub gc_deviation: f64,
+    /// Reads used to estimate duplication.
+    pub dup_reads: u64,
+    pub percent_unique: f64,
+    pub dup_levels: Vec<DupLevel>,
+    pub overrepresented: Vec<Overrepresented>,
+    pub adapter_content: Vec<AdapterProfile>,
+    pub enriched_kmers: Vec<EnrichedKmer>,
+    pub lanes: Vec<LaneSummary>,
+    pub tiles: Vec<TileSummary>,
+    /// Reads with a Casava 1.8 comment.
+    pub casava_reads: u64,
+    /// Reads flagged by the chastity filter.
+    pub filtered_reads: u64,
+    pub top_indexes: Vec<IndexSummary>,
+    /// Instrument, run, flowcell, and lane combinations.
+    pub run_lanes: Vec<RunLane>,
     sum_qscores: f64,
 }
 
 impl FastqStats {
-    pub fn count_all_reads(fname: &PathBuf, 
-                            reads: &u32,
-                            vec: &[SeqReads], 
-                            qscores: &[QScore]
-        ) -> Self {
-        let seq_len = vec.iter().map(|v| v.seq_len).collect::<Vec<u32>>();
-
+    pub fn count_all_reads(fname: &Path, reads: &ReadAccumulator, params: &QcParams) -> Self {
+        let gc = reads.gc_dist.fit_normal();
+        let dups = reads.duplication.summarize();
         let mut seq = Self {
             path: fname.parent().unwrap().to_string_lossy().into_owned(),
-            seqname: fname.file_name().unwrap().to_string_lossy().into_owned(),
-            read_count: *reads,
-            total_bp: seq_len.iter().sum::<u32>() as u64,
-            min_reads: *seq_len.iter().min().unwrap(),
-            max_reads: *seq_len.iter().max().unwrap(),
-            median_reads: stats::median(&seq_len),
-            total_gc: vec.iter().map(|v| v.gc_count).sum(),
-            total_n: vec.iter().map(|v| v.n_count).sum(),
-            sum_qlen: qscores.iter().map(|q| q.q_len).sum::<u32>() as u64,
-            sum_low_bases: qscores.iter().map(|q| q.low_bases).sum(),
-            sum_qscores: qscores.iter().map(|q| q.mean_q).sum(),
+            seqname: reader::sample_name(fname),
+            read_count: reads.read_count,
+            total_bp: reads.total_bp,
+            min_reads: reads.seq_len.min(),
+            max_reads: reads.seq_len.max(),
+            median_reads: reads.seq_len.median(),
+            total_gc: reads.total_gc,
+            total_n: reads.total_n,
+            sum_qlen: reads.sum_qlen,
+            sum_low_bases: reads.sum_low_bases,
+            sum_qscores: reads.sum_qscores,
             mean_reads: 0.0,
-            sd_reads: 0.0,
+            sd_reads: reads.seq_var.stdev(),
             gc_content: 0.0,
+            gc_content_no_tails: reads.tails.gc_content_without(reads.total_gc, reads.total_bp),
+            poly_g_reads: reads.tails.poly_g(),
+            poly_a_reads: reads.tails.poly_a(),
+            poly_t_reads: reads.tails.poly_t(),
+            mean_tail_len: reads.tails.mean_len(),
             n_content: 0.0,
             mean_qscores: 0.0,
-            low_bases_ratio: 0.0
+            error_mean_qscores: qscores::error_to_qscore(
+                reads.sum_expected_errors / reads.sum_qlen as f64),
+            mean_expected_errors: reads.sum_expected_errors / reads.read_count as f64,
+            ee_hist: reads.expected_errors.histogram(),
+            low_bases_ratio: 0.0,
+            low_q: params.low_q,
+            q20_ratio: reads.sum_q20_bases as f64 / reads.sum_qlen as f64,
+            q30_ratio: reads.sum_q30_bases as f64 / reads.sum_qlen as f64,
+            mean_q_hist: reads.read_quality.histogram(),
+            mate: None,
+            pair_status: PairStatus::SingleEnd,
+            mates: Vec::new(),
+            encoding: QEncoding::Phred33,
+            cycle_quality: reads.cycle_quality.summarize(),
+            base_content: reads.cycle_bases.summarize(),
+            length_hist: reads.seq_len.histogram(params.length_bins),
+            gc_hist: gc.bins,
+            gc_deviation: gc.deviation,
+            dup_reads: dups.reads,
+            percent_unique: dups.percent_unique,
+            dup_levels: dups.levels,
+            overrepresented: reads.duplication.overrepresented(params.overrep_min),
+            adapter_content: reads.adapter_content.summarize(&params.adapters),
+            enriched_kmers: reads.kmers.enriched(params.kmer_size),
+            lanes: reads.tiles.lanes(),
+            tiles: reads.tiles.tiles(),
+            casava_reads: reads.indexes.reads(),
+            filtered_reads: reads.indexes.filtered(),
+            top_indexes: reads.indexes.top_indexes(),
+            run_lanes: reads.runs.lanes(),
         }; 
 
         seq.gc_content();
         seq.n_content();
         seq.mean_seq();
-        seq.stdev(&seq_len);
         seq.mean_q();
         seq.low_bases();
 
         seq
     }
+
+    pub fn set_mate(&mut self, mate: &Path, status: PairStatus) {
+        self.mate = Some(reader::sample_name(mate));
+        self.pair_status = status;
+    }
+
+    /// Adds mate 1 and mate 2 stats of an interleaved file.
+    pub fn set_interleaved_mates(&mut self, mates: &[ReadAccumulator], params: &QcParams) {
+        let fname = Path::new(&self.path).join(&self.seqname);
+
+        self.mates = mates.iter()
+            .enumerate()
+            .map(|(idx, reads)| {
+                let mut stats = FastqStats::count_all_reads(&fname, reads, params);
+                stats.seqname = format!("{} (mate {})", self.seqname, idx + 1);
+                stats.pair_status = PairStatus::Interleaved;
+                stats.encoding = self.encoding;
+                stats
+            })
+            .collect();
+        self.pair_status = PairStatus::Interleaved;
+    }
+
+    /// Renames the sample and its interleaved mates.
+    pub fn set_seqname(&mut self, name: &str) {
+        self.mates.iter_mut()
+            .enumerate()
+            .for_each(|(idx, stats)| {
+                stats.seqname = format!("{} (mate {})", name, idx + 1);
+            });
+        self.seqname = name.to_string();
+    }
     
+    pub fn is_gc_unusual(&self) -> bool {
+        self.gc_deviation > GC_DEVIATION_WARN
+    }
+
+    /// Adapter found in the most reads.
+    pub fn max_adapter(&self) -> Option<&AdapterProfile> {
+        self.adapter_content.iter()
+            .max_by(|a, b| a.total().total_cmp(&b.total()))
+    }
+
+    pub fn needs_trimming(&self) -> bool {
+        self.max_adapter()
+            .is_some_and(|adapter| adapter.total() > ADAPTER_WARN)
+    }
+
+    /// Percentage of filtered reads. 
+    /// None without Casava 1.8 comments.
+    pub fn filtered_percent(&self) -> Option<f64> {
+        (self.casava_reads > 0)
+            .then(|| self.filtered_reads as f64 / self.casava_reads as f64 * 100.0)
+    }
+
+    /// Number of distinct sequencing runs and flowcells.
+    pub fn run_count(&self) -> usize {
+        runs::count_runs(&self.run_lanes)
+    }
+
+    /// Whether the reads come from more than one run or flowcell.
+    /// Lanes of the same flowcell are not mixed.
+    pub fn is_mixed_run(&self) -> bool {
+        self.run_count() > 1
+    }
+
+    /// Tile with the lowest mean q-score.
+    pub fn worst_tile(&self) -> Option<&TileSummary> {
+        self.tiles.iter()
+            .min_by(|a, b| a.mean_q.total_cmp(&b.mean_q))
+    }
+
     fn gc_content(&mut self) {
         self.gc_content = self.total_gc as f64 / self.total_bp as f64;
     }
@@ -108,10 +392,6 @@ impl FastqStats {
         self.mean_reads = self.total_bp as f64 / self.read_count as f64;
     }
 
-    fn stdev(&mut self, seq_len: &[u32]) {
-        self.sd_reads = stats::stdev(&seq_len, &self.mean_reads);
-    }
-
     fn mean_q(&mut self) {
         self.mean_qscores = self.sum_qscores / self.read_count as f64;
     }
@@ -121,60 +401,87 @@ impl FastqStats {
     }
 }
 
+/// Running totals of FASTA contigs.
+#[derive(Default)]
+pub struct ContigAccumulator {
+    pub contig_counts: u64,
+    total_bp: u64,
+    total_gc: u64,
+    total_n: u64,
+    contig_len: LengthDist,
+    contig_var: OnlineVariance,
+}
+
+impl ContigAccumulator {
+    pub fn add_contig(&mut self, contig: &SeqReads) {
+        self.contig_counts += 1;
+        self.total_bp += contig.seq_len;
+        self.total_gc += contig.gc_count;
+        self.total_n += contig.n_count;
+        self.contig_len.add(contig.seq_len);
+        self.contig_var.add(contig.seq_len as f64);
+    }
+
+    pub fn merge(&mut self, other: &ContigAccumulator) {
+        self.contig_counts += other.contig_counts;
+        self.total_bp += other.total_bp;
+        self.total_gc += other.total_gc;
+        self.total_n += other.total_n;
+        self.contig_len.merge(&other.contig_len);
+        self.contig_var.merge(&other.contig_var);
+    }
+}
+
 pub struct FastaStats {
     pub path: String,
     pub seqname: String,
-    pub contig_counts: u32,
-    pub total_gc: u32,
-    pub total_bp: u32,
-    pub total_n: u32,
+    pub contig_counts: u64,
+    pub total_gc: u64,
+    pub total_bp: u64,
+    pub total_n: u64,
     pub gc_content: f64,
     pub n_content: f64, 
     pub mean: f64,
-    pub min: u32,
-    pub max: u32,
+    pub min: u64,
+    pub max: u64,
     pub median: f64,
     pub sd: f64,
-    pub n50: u32,
-    pub n75: u32,
-    pub n90: u32,
+    pub n50: u64,
+    pub n75: u64,
+    pub n90: u64,
     pub con750: usize,
     pub con1000: usize,
     pub con1500: usize,
 }
 
 impl FastaStats {
-    pub fn get_stats(input: &PathBuf, contigs: &u32, seq: &[SeqReads]) -> Self {
+    pub fn get_stats(input: &Path, seq: &ContigAccumulator) -> Self {
         let mut con = Self {
             path: input.parent().unwrap().to_string_lossy().into_owned(),
-            seqname: input.file_name().unwrap().to_string_lossy().into_owned(),
-            contig_counts : *contigs,
-            total_bp: seq.iter().map(|s| s.seq_len).sum(),
-            total_gc: seq.iter().map(|s| s.gc_count).sum(),
-            total_n: seq.iter().map(|n| n.n_count).sum(),
+            seqname: reader::sample_name(input),
+            contig_counts : seq.contig_counts,
+            total_bp: seq.total_bp,
+            total_gc: seq.total_gc,
+            total_n: seq.total_n,
             gc_content: 0.0,
             n_content: 0.0,
-            min: seq.iter().map(|s| s.seq_len).min().unwrap(),
-            max: seq.iter().map(|s| s.seq_len).max().unwrap(),
+            min: seq.contig_len.min(),
+            max: seq.contig_len.max(),
             n50: 0,
             n75: 0,
             n90: 0,
-            con750: seq.iter().map(|s| s.seq_len).filter(|s| *s > 750).count(),
-            con1000: seq.iter().map(|s| s.seq_len).filter(|s| *s > 1000).count(),
-            con1500: seq.iter().map(|s| s.seq_len).filter(|s| *s > 1500).count(),
+            con750: seq.contig_len.count_above(750),
+            con1000: seq.contig_len.count_above(1000),
+            con1500: seq.contig_len.count_above(1500),
             mean: 0.0,
-            median: 0.0,
-            sd: 0.0,
+            median: seq.contig_len.median(),
+            sd: seq.contig_var.stdev(),
         };
-
-        let contigs = seq.iter().map(|s| s.seq_len).collect::<Vec<u32>>();
     
         con.gc_content();
         con.n_content();
         con.mean();
-        con.median(&contigs);
-        con.stdev(&contigs);
-        con.nstats(&contigs);
+        con.nstats(&seq.contig_len);
 
         con
     }
@@ -191,15 +498,7 @@ impl FastaStats {
         self.mean = self.total_bp as f64 / self.contig_counts as f64;
     }
 
-    fn median(&mut self, contigs: &[u32]) {
-        self.median = stats::median(&contigs);
-    }
-
-    fn stdev(&mut self, contigs: &[u32]) {
-        self.sd = stats::stdev(contigs, &self.mean);
-    }
-
-    fn nstats(&mut self, contigs: &[u32]) {
+    fn nstats(&mut self, contigs: &LengthDist) {
         let mut stats = NStats::new(contigs);
         stats.get_n50();
         stats.get_n75();
@@ -214,8 +513,47 @@ impl FastaStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
     use assert_approx_eq::assert_approx_eq;
 
+    // Read with only the sequence stats and q-scores set.
+    fn read_info<'a>(seq: &'a [u8], phred: &'a [u8], qscores: QScore) -> ReadInfo<'a> {
+        ReadInfo {
+            seq,
+            phred,
+            reads: SeqReads::get_seq_stats(seq),
+            qscores,
+            dup_hash: None,
+            adapter_hits: &[],
+            kmers: &[],
+            tail: None,
+            id: None,
+            comment: None,
+        }
+    }
+
+    // Stats of the reads with the default parameters.
+    fn read_stats(reads: &[ReadInfo]) -> FastqStats {
+        let mut acc = ReadAccumulator::default();
+        reads.iter().for_each(|read| acc.add_read(read));
+
+        FastqStats::count_all_reads(Path::new("data/test.fastq"), &acc, &QcParams::default())
+    }
+
+    // Q-scores of q_len bases at Q40.
+    fn q40(q_len: u32) -> QScore {
+        QScore {
+            q_len,
+            mean_q: 40.0,
+            low_bases: 0,
+            q20_bases: q_len,
+            q30_bases: q_len,
+            sum: 40 * q_len,
+            expected_errors: 0.0001 * q_len as f64,
+            error_mean_q: 40.0,
+        }
+    }
+
     #[test]
     fn gc_count_test() {
         let a = String::from("AA");
@@ -225,11 +563,11 @@ mod tests {
         let e = String::from("aataNctgn");
         let f = b"aacc";
 
-        let seq_a: SeqReads = SeqReads::get_seq_stats(&a.as_bytes());
-        let seq_b: SeqReads = SeqReads::get_seq_stats(&b.as_bytes());
-        let seq_c: SeqReads = SeqReads::get_seq_stats(&c.as_bytes());
-        let seq_d: SeqReads = SeqReads::get_seq_stats(&d.as_bytes());
-        let seq_e: SeqReads = SeqReads::get_seq_stats(&e.as_bytes());
+        let seq_a: SeqReads = SeqReads::get_seq_stats(a.as_bytes());
+        let seq_b: SeqReads = SeqReads::get_seq_stats(b.as_bytes());
+        let seq_c: SeqReads = SeqReads::get_seq_stats(c.as_bytes());
+        let seq_d: SeqReads = SeqReads::get_seq_stats(d.as_bytes());
+        let seq_e: SeqReads = SeqReads::get_seq_stats(e.as_bytes());
         let seq_f: SeqReads = SeqReads::get_seq_stats(f);
 
 
@@ -253,32 +591,13 @@ mod tests {
         let a: String = String::from("ttggcc");
         let b: String = String::from("taNctgncca");
         
-        let q = QScore {
-                q_len: 2,
-                mean_q: 40.0,
-                low_bases: 0,
-                sum: 40,
-            };
-        let q_two = QScore {
-                q_len: 2,
-                mean_q: 40.0,
-                low_bases: 0,
-                sum: 40
-            };
-        
-        let mut seq: Vec<SeqReads> = Vec::new();
-        let seq_a = SeqReads::get_seq_stats(&a.as_bytes());
-        seq.push(seq_a);
-
-        let seq_b = SeqReads::get_seq_stats(&b.as_bytes());
-        seq.push(seq_b);
-        
-        let qscores: Vec<QScore> = vec![q, q_two];
+        let mut reads = ReadAccumulator::default();
+        reads.add_read(&read_info(a.as_bytes(), &[40, 40], q40(2)));
+        reads.add_read(&read_info(b.as_bytes(), &[40, 40], q40(2)));
 
         let fname = PathBuf::from("data/test.fastq");
-        let reads = 2;
 
-        let res = FastqStats::count_all_reads(&fname, &reads, &seq, &qscores);
+        let res = FastqStats::count_all_reads(&fname, &reads, &QcParams::default());
 
         assert_eq!("test.fastq", res.seqname);
         assert_eq!(2, res.read_count);
@@ -290,30 +609,173 @@ mod tests {
         assert_eq!(6, res.min_reads);
         assert_eq!(10, res.max_reads);
         assert_eq!(8.0, res.mean_reads);
+        assert_eq!(8.0, res.median_reads);
+        assert_approx_eq!(2.828427, res.sd_reads, 1e-6);
         assert_eq!(0, res.sum_low_bases);
         assert_eq!(40.0, res.mean_qscores);
         assert_eq!(0.0, res.low_bases_ratio);
     }
     
+    #[test]
+    fn cycle_quality_stats_test() {
+        let res = read_stats(&[
+            read_info(b"ACGT", &[30, 40], q40(2)),
+            read_info(b"ACGT", &[20, 40, 10], q40(3)),
+        ]);
+
+        assert_eq!(3, res.cycle_quality.len());
+        assert_eq!(25.0, res.cycle_quality[0].mean);
+        assert_eq!(40.0, res.cycle_quality[1].mean);
+        assert_eq!(1, res.cycle_quality[2].reads);
+        assert_eq!(10.0, res.cycle_quality[2].mean);
+    }
+
+    #[test]
+    fn base_content_stats_test() {
+        let res = read_stats(&[
+            read_info(b"ACGTA", &[40; 5], q40(5)),
+            read_info(b"ANTT", &[40; 4], q40(4)),
+        ]);
+
+        assert_eq!(5, res.base_content.len());
+        assert_eq!(1.0, res.base_content[0].a);
+        assert_eq!(0.5, res.base_content[1].c);
+        assert_eq!(0.5, res.base_content[1].n);
+        assert_eq!(1, res.base_content[4].reads);
+    }
+
+    #[test]
+    fn length_hist_stats_test() {
+        let long = vec![b'A'; 25];
+        let res = read_stats(&[
+            read_info(b"ACGTA", &[40; 5], q40(5)),
+            read_info(b"ACGTACGT", &[40; 8], q40(8)),
+            read_info(&long, &[40; 25], q40(25)),
+        ]);
+
+        let hist: Vec<(u64, u64, u64)> = res.length_hist.iter()
+            .map(|bin| (bin.start, bin.end, bin.count))
+            .collect();
+
+        assert_eq!(vec![(0, 9, 2), (10, 19, 0), (20, 29, 1)], hist);
+    }
+
+    #[test]
+    fn read_quality_stats_test() {
+        let (mut phred_a, mut phred_b) = (Vec::new(), Vec::new());
+        let qscores_a = QScore::analyze_qscores(b"II++", QEncoding::Phred33, 20, &mut phred_a)
+            .unwrap();
+        let qscores_b = QScore::analyze_qscores(b"5555", QEncoding::Phred33, 20, &mut phred_b)
+            .unwrap();
+        let res = read_stats(&[
+            read_info(b"ACGT", &phred_a, qscores_a),
+            read_info(b"ACGT", &phred_b, qscores_b),
+        ]);
+        let hist = &res.mean_q_hist;
+
+        assert_eq!(0.75, res.q20_ratio);
+        assert_eq!(0.25, res.q30_ratio);
+        assert_eq!(13, hist[0].q);
+        assert_eq!(25, hist[hist.len() - 1].q);
+        assert_eq!(1, hist[0].error_count);
+        assert_eq!(0, hist[0].count);
+        assert_eq!(1, hist[7].count);
+        assert_eq!(1, hist[12].count);
+    }
+
+    #[test]
+    fn expected_errors_stats_test() {
+        let (mut phred_a, mut phred_b) = (Vec::new(), Vec::new());
+        let qscores_a = QScore::analyze_qscores(b"II++", QEncoding::Phred33, 20, &mut phred_a)
+            .unwrap();
+        let qscores_b = QScore::analyze_qscores(b"!!!", QEncoding::Phred33, 20, &mut phred_b)
+            .unwrap();
+        let res = read_stats(&[
+            read_info(b"ACGT", &phred_a, qscores_a),
+            read_info(b"ACG", &phred_b, qscores_b),
+        ]);
+        let counts: Vec<u64> = res.ee_hist.iter().map(|bin| bin.count).collect();
+
+        assert_approx_eq!(1.6001, res.mean_expected_errors, 1e-9);
+        assert_approx_eq!(qscores::error_to_qscore(3.2002 / 7.0), res.error_mean_qscores, 1e-9);
+        assert_eq!(vec![1, 0, 0, 1, 0, 0, 0], counts);
+        assert_eq!(0.5, res.ee_hist[0].cumulative);
+        assert_eq!(Some(3.0), res.ee_hist[3].max);
+    }
+
+    #[test]
+    fn duplication_stats_test() {
+        let dup = |hash| ReadInfo { dup_hash: Some(hash), ..read_info(b"ACGT", &[40; 4], q40(4)) };
+        let res = read_stats(&[
+            dup(1),
+            dup(1),
+            dup(1),
+            dup(2),
+            read_info(b"ACGT", &[40; 4], q40(4)),
+        ]);
+
+        assert_eq!(4, res.dup_reads);
+        assert_eq!(50.0, res.percent_unique);
+        assert_eq!(1, res.dup_levels[0].sequences);
+        assert_eq!(1, res.dup_levels[2].sequences);
+        assert_eq!(3, res.dup_levels[2].reads);
+        assert_eq!(0.75, res.dup_levels[2].read_ratio);
+    }
+
+    #[test]
+    fn adapter_content_stats_test() {
+        let hits = |hits| ReadInfo { adapter_hits: hits, ..read_info(b"ACGT", &[40; 4], q40(4)) };
+        let res = read_stats(&[
+            hits(&[Some(2), None, None, None]),
+            hits(&[None, Some(0), None, None]),
+            hits(&[Some(1), None, None, None]),
+            hits(&[None, None, None, None]),
+        ]);
+
+        assert_eq!(4, res.adapter_content.len());
+        assert_eq!(vec![0.0, 25.0, 50.0], res.adapter_content[0].content);
+        assert_eq!(25.0, res.adapter_content[1].total());
+        assert_eq!(0.0, res.adapter_content[3].total());
+        assert_eq!("Illumina TruSeq", res.max_adapter().unwrap().name);
+        assert!(res.needs_trimming());
+    }
+
+    #[test]
+    fn poly_tail_stats_test() {
+        let tail = |seq, poly| ReadInfo { tail: Some(poly), ..read_info(seq, &[], q40(0)) };
+        let res = read_stats(&[
+            tail(b"ACGTGGGGGG", PolyTail { base: 0, len: 6, gc: 6 }),
+            tail(b"ACGTAAAAAA", PolyTail { base: 1, len: 6, gc: 0 }),
+            tail(b"ACGTTTTT", PolyTail { base: 2, len: 4, gc: 0 }),
+            read_info(b"ACGT", &[], q40(0)),
+        ]);
+
+        assert_eq!(1, res.poly_g_reads);
+        assert_eq!(1, res.poly_a_reads);
+        assert_eq!(1, res.poly_t_reads);
+        assert_eq!(16.0 / 3.0, res.mean_tail_len);
+        assert_eq!(14, res.total_gc);
+        assert_eq!(0.5, res.gc_content_no_tails);
+    }
+
     #[test]
     fn fasta_stats_test() {
         let fname = PathBuf::from("data/test.fasta");
         let a = "AA";
         let b = "AAGC";
-        let contigs = 1;
 
-        let mut seq: Vec<SeqReads> = Vec::new();
-        let seq_a = SeqReads::get_seq_stats(&a.as_bytes());
-        seq.push(seq_a);
+        let mut seq = ContigAccumulator::default();
+        let seq_a = SeqReads::get_seq_stats(a.as_bytes());
+        seq.add_contig(&seq_a);
 
-        let seq_b = SeqReads::get_seq_stats(&b.as_bytes());
-        seq.push(seq_b);
+        let seq_b = SeqReads::get_seq_stats(b.as_bytes());
+        seq.add_contig(&seq_b);
 
-        let cont = FastaStats::get_stats(&fname, &contigs, &seq);
+        let cont = FastaStats::get_stats(&fname, &seq);
 
         assert_eq!("data", cont.path);
         assert_eq!("test.fasta", cont.seqname);
-        assert_eq!(1, cont.contig_counts);
+        assert_eq!(2, cont.contig_counts);
         assert_eq!(6, cont.total_bp);
         assert_eq!(0, cont.total_n);
         assert_eq!(2, cont.total_gc);
@@ -325,4 +787,82 @@ mod tests {
         assert_approx_eq!(0.333, cont.gc_content, 3f64);
         assert_approx_eq!(3.0, cont.median, 1f64);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn large_read_counts_test() {
+        let qscores = QScore {
+            q_len: 2_000_000_000,
+            mean_q: 30.0,
+            low_bases: 1_500_000_000,
+            q20_bases: 500_000_000,
+            q30_bases: 0,
+            sum: 0,
+            expected_errors: 0.0,
+            error_mean_q: 0.0,
+        };
+        let read = ReadInfo {
+            reads: SeqReads {
+                seq_len: 2_000_000_000,
+                gc_count: 1_500_000_000,
+                n_count: 1_000_000_000,
+            },
+            ..read_info(&[], &[], qscores)
+        };
+
+        let mut acc = ReadAccumulator::default();
+        (0..3).for_each(|_| acc.add_read(&read));
+
+        let fname = PathBuf::from("data/test.fastq");
+        let res = FastqStats::count_all_reads(&fname, &acc, &QcParams::default());
+
+        assert_eq!(6_000_000_000, res.total_bp);
+        assert_eq!(4_500_000_000, res.total_gc);
+        assert_eq!(3_000_000_000, res.total_n);
+        assert_eq!(6_000_000_000, res.sum_qlen);
+        assert_eq!(4_500_000_000, res.sum_low_bases);
+        assert_eq!(0.75, res.gc_content);
+        assert_eq!(0.75, res.low_bases_ratio);
+    }
+
+    #[test]
+    fn large_assembly_test() {
+        let contig = SeqReads {
+            seq_len: 3_000_000_000,
+            gc_count: 1_200_000_000,
+            n_count: 300_000_000,
+        };
+
+        let mut acc = ContigAccumulator::default();
+        (0..2).for_each(|_| acc.add_contig(&contig));
+
+        let fname = PathBuf::from("data/test.fasta");
+        let res = FastaStats::get_stats(&fname, &acc);
+
+        assert_eq!(6_000_000_000, res.total_bp);
+        assert_eq!(2_400_000_000, res.total_gc);
+        assert_eq!(600_000_000, res.total_n);
+        assert_eq!(3_000_000_000, res.n50);
+        assert_eq!(0.4, res.gc_content);
+    }
+
+    #[test]
+    fn contig_above_u32_test() {
+        let contig = SeqReads {
+            seq_len: 5_000_000_000,
+            gc_count: 2_000_000_000,
+            n_count: 0,
+        };
+
+        let mut acc = ContigAccumulator::default();
+        acc.add_contig(&contig);
+
+        let fname = PathBuf::from("data/test.fasta");
+        let res = FastaStats::get_stats(&fname, &acc);
+
+        assert_eq!(5_000_000_000, res.min);
+        assert_eq!(5_000_000_000, res.max);
+        assert_eq!(5_000_000_000, res.n50);
+        assert_eq!(5_000_000_000, res.n90);
+        assert_eq!(0.4, res.gc_content);
+    }
+}
@@ -1,31 +1,314 @@
 //! Heru Handika
 //! Module for statistics
 
-use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// Sequence length distribution stored as counts per length.
+/// Memory depends on the number of distinct lengths, 
+/// not on the number of sequences.
+#[derive(Default)]
+pub struct LengthDist {
+    counts: BTreeMap<u64, u64>,
+    total: u64,
+}
+
+impl LengthDist {
+    pub fn add(&mut self, len: u64) {
+        *self.counts.entry(len).or_insert(0) += 1;
+        self.total += 1;
+    }
+
+    pub fn merge(&mut self, other: &LengthDist) {
+        other.counts.iter()
+            .for_each(|(len, count)| *self.counts.entry(*len).or_insert(0) += count);
+        self.total += other.total;
+    }
+
+    pub fn min(&self) -> u64 {
+        self.counts.keys().next().copied().unwrap_or(0)
+    }
+
+    pub fn max(&self) -> u64 {
+        self.counts.keys().next_back().copied().unwrap_or(0)
+    }
+
+    pub fn median(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+
+        let midpoint = self.total / 2;
+        
+        if self.total.is_multiple_of(2) {
+            (self.nth(midpoint - 1) as f64 + self.nth(midpoint) as f64) / 2.0
+        } else {
+            self.nth(midpoint) as f64
+        }
+    }
+
+    /// Counts sequences longer than len.
+    pub fn count_above(&self, len: u64) -> usize {
+        self.counts.range(len + 1..)
+            .map(|(_, count)| *count as usize)
+            .sum()
+    }
+
+    /// Counts sequences in bins from the shortest to the longest.
+    /// Empty bins in between are kept.
+    pub fn histogram(&self, bins: LengthBins) -> Vec<LengthBin> {
+        if self.total == 0 {
+            return Vec::new();
+        }
+
+        let first = bins.index(self.min());
+        let mut hist: Vec<LengthBin> = (first..=bins.index(self.max()))
+            .map(|idx| bins.bin(idx))
+            .collect();
+
+        self.counts.iter()
+            .for_each(|(len, count)| hist[bins.index(*len) - first].count += count);
+
+        hist
+    }
+
+    // Length at the nth position of the sorted lengths.
+    fn nth(&self, idx: u64) -> u64 {
+        let mut csum = 0;
+        for (len, count) in self.counts.iter() {
+            csum += count;
+            if csum > idx {
+                return *len;
+            }
+        }
+
+        self.max()
+    }
+}
+
+/// Bins of the length histogram.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LengthBins {
+    /// Bins of a fixed width.
+    Width(u32),
+    /// Bins doubling in width (1, 2-3, 4-7, ...) for long reads.
+    Log2,
+}
+
+impl Default for LengthBins {
+    fn default() -> Self {
+        LengthBins::Width(10)
+    }
+}
+
+impl LengthBins {
+    fn index(&self, len: u64) -> usize {
+        match self {
+            LengthBins::Width(width) => (len / *width as u64) as usize,
+            LengthBins::Log2 => (u64::BITS - len.leading_zeros()) as usize,
+        }
+    }
+
+    fn bin(&self, idx: usize) -> LengthBin {
+        let (start, end) = match self {
+            LengthBins::Width(width) => {
+                let start = idx as u64 * *width as u64;
+                (start, start.saturating_add(*width as u64 - 1))
+            }
+            LengthBins::Log2 if idx == 0 => (0, 0),
+            LengthBins::Log2 => (1 << (idx - 1), 1u64.checked_shl(idx as u32).map_or(u64::MAX, |end| end - 1)),
+        };
+
+        LengthBin { start, end, count: 0 }
+    }
+}
+
+/// Number of sequences with lengths from start to end (inclusive).
+#[derive(Debug, PartialEq)]
+pub struct LengthBin {
+    pub start: u64,
+    pub end: u64,
+    pub count: u64,
+}
+
+/// Per-read GC content in 1% bins.
+pub struct GcDist {
+    counts: [f64; GC_BINS],
+}
+
+const GC_BINS: usize = 101;
+
+impl Default for GcDist {
+    fn default() -> Self {
+        Self { counts: [0.0; GC_BINS] }
+    }
+}
+
+impl GcDist {
+    /// Adds a read. N bases are excluded from the read length.
+    /// Reads without called bases are skipped. As in FastQC, 
+    /// the read is spread over the bins its GC count covers, 
+    /// so read lengths other than 100 don't leave empty bins.
+    pub fn add(&mut self, gc_count: u64, called_bases: u64) {
+        if called_bases == 0 {
+            return;
+        }
+
+        let len = called_bases as f64;
+        let low = ((gc_count as f64 - 0.5) * 100.0 / len).max(0.0);
+        let high = ((gc_count as f64 + 0.5) * 100.0 / len).min(100.0);
+        let first = (low + 0.5).floor() as usize;
+        let last = ((high + 0.5).floor() as usize).min(GC_BINS - 1);
+
+        (first..=last).for_each(|pct| {
+            let overlap = high.min(pct as f64 + 0.5) - low.max(pct as f64 - 0.5);
+            if overlap > 0.0 {
+                self.counts[pct] += overlap / (high - low);
+            }
+        });
+    }
+
+    pub fn merge(&mut self, other: &GcDist) {
+        self.counts.iter_mut()
+            .zip(other.counts.iter())
+            .for_each(|(count, other)| *count += other);
+    }
+
+    /// Compares the histogram with a normal distribution
+    /// of the same mean and standard deviation.
+    pub fn fit_normal(&self) -> GcProfile {
+        let total: f64 = self.counts.iter().sum();
+        if total == 0.0 {
+            return GcProfile { bins: Vec::new(), deviation: 0.0 };
+        }
+
+        let mean = self.counts.iter()
+            .enumerate()
+            .map(|(pct, count)| pct as f64 * count)
+            .sum::<f64>() / total;
+        let sd = (self.counts.iter()
+            .enumerate()
+            .map(|(pct, count)| (pct as f64 - mean).powi(2) * count)
+            .sum::<f64>() / (total - 1.0)).sqrt();
+
+        let theoretical = |pct: f64| {
+            if sd > 0.0 {
+                total * (normal_cdf(pct + 0.5, mean, sd) 
+                    - normal_cdf(pct - 0.5, mean, sd))
+            } else if pct == mean.round() {
+                total
+            } else {
+                0.0
+            }
+        };
+
+        let bins: Vec<GcBin> = self.counts.iter()
+            .enumerate()
+            .map(|(pct, count)| GcBin {
+                gc: pct as u32,
+                count: *count,
+                theoretical: theoretical(pct as f64),
+            })
+            .collect();
+
+        let deviation = bins.iter()
+            .map(|bin| (bin.count - bin.theoretical).abs())
+            .sum::<f64>() / total * 100.0;
+
+        GcProfile { bins, deviation }
+    }
+}
+
+/// Observed and expected read counts at a GC percentage.
+/// Observed counts are fractional, since reads are spread
+/// over the bins they cover.
+pub struct GcBin {
+    pub gc: u32,
+    pub count: f64,
+    pub theoretical: f64,
+}
+
+pub struct GcProfile {
+    pub bins: Vec<GcBin>,
+    /// Percentage of reads deviating from the normal distribution.
+    pub deviation: f64,
+}
+
+fn normal_cdf(x: f64, mean: f64, sd: f64) -> f64 {
+    0.5 * (1.0 + erf((x - mean) / (sd * std::f64::consts::SQRT_2)))
+}
+
+// Abramowitz and Stegun 7.1.26. Max error 1.5e-7.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t * (0.254829592 
+        + t * (-0.284496736 
+        + t * (1.421413741 
+        + t * (-1.453152027 
+        + t * 1.061405429))));
+    let y = 1.0 - poly * (-x * x).exp();
+
+    y.copysign(x)
+}
 
+/// Welford's online algorithm for mean and variance.
+#[derive(Default)]
+pub struct OnlineVariance {
+    n: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl OnlineVariance {
+    pub fn add(&mut self, val: f64) {
+        self.n += 1;
+        let delta = val - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (val - self.mean);
+    }
+
+    /// Combines two partial variances (Chan et al.).
+    pub fn merge(&mut self, other: &OnlineVariance) {
+        if other.n == 0 {
+            return;
+        }
+
+        let n = self.n + other.n;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.n as f64 / n as f64;
+        self.m2 += other.m2 + delta * delta * (self.n as f64 * other.n as f64) / n as f64;
+        self.n = n;
+    }
+
+    pub fn stdev(&self) -> f64 {
+        let var = self.m2 / (self.n as f64 - 1.0);
+        var.sqrt()
+    }
+}
+
+// Reference implementations for the online stats.
+#[cfg(test)]
 #[inline]
-fn sort_vector_asc(vec: &[u32]) -> Vec<u32> {
+fn sort_vector_asc(vec: &[u64]) -> Vec<u64> {
     let mut sorted_vec = vec.to_vec();
     sorted_vec.sort_unstable();
 
     sorted_vec
 }
 
-pub fn median(vec: &[u32]) -> f64 {
-    let sorted_vec = sort_vector_asc(&vec);
+#[cfg(test)]
+pub fn median(vec: &[u64]) -> f64 {
+    let sorted_vec = sort_vector_asc(vec);
     let n = sorted_vec.len();
     let midpoint = n / 2;
 
-    let med;
-    if n % 2 == 0  {
-        med = (sorted_vec [midpoint - 1]  + sorted_vec[midpoint])  as f64 / 2.0
+    if n.is_multiple_of(2)  {
+        (sorted_vec [midpoint - 1]  + sorted_vec[midpoint])  as f64 / 2.0
     } else {
-        med = sorted_vec[midpoint] as f64
+        sorted_vec[midpoint] as f64
     }
-    
-    med 
 }
 
+#[cfg(test)]
 #[inline(always)]
 fn sum_of_square(vec: &[f64]) -> f64 {
     let d: f64 = vec.iter()
@@ -35,104 +318,92 @@ fn sum_of_square(vec: &[f64]) -> f64 {
     d
 }
 
+#[cfg(test)]
 #[inline(always)]
-fn dev_mean(vec: &[u32], mean: &f64) -> Vec<f64> {
+fn dev_mean(vec: &[u64], mean: &f64) -> Vec<f64> {
     vec.iter()
         .map(|&val| val as f64 - *mean)
         .collect()
 }
 
-fn variance(vec: &[u32], mean: &f64) -> f64 {
+#[cfg(test)]
+fn variance(vec: &[u64], mean: &f64) -> f64 {
     let d_mean = dev_mean(vec, mean);
     let n = vec.len() as f64 - 1.0 ;
     
     sum_of_square(&d_mean) / n
 }
 
-pub fn stdev(vec: &[u32], mean: &f64) -> f64 {
+#[cfg(test)]
+pub fn stdev(vec: &[u64], mean: &f64) -> f64 {
     let var = variance(vec, mean);
     var.sqrt()
 }
 
-
-fn sort_vec_desc(vec: &[u32]) -> Vec<u32> {
-    let mut sorted_vec = vec.to_vec();
-    sorted_vec.sort_by_key(|v| Reverse(*v));
-
-    sorted_vec
-}
-
-fn cumsum(vec: &[u32]) -> Vec<u32> {
-    let mut csum = Vec::new();
-    let mut sum = 0;
-    vec.iter()
-        .for_each(|v|{
-            sum += v;
-            csum.push(sum);
-        });
-    
-    csum
-}
-
 pub struct NStats {
-    sorted_contigs: Vec<u32>,
-    csum_contigs: Vec<u32>,
-    sum_contigs: u32,
-    n50_len: u32,
-    n75_len: u32,
-    n90_len: u32,
-    pub n50: u32,
-    pub n75: u32,
-    pub n90: u32,
+    // Contig lengths and counts, longest first.
+    contigs: Vec<(u64, u64)>,
+    sum_contigs: u64,
+    n50_len: u64,
+    n75_len: u64,
+    n90_len: u64,
+    pub n50: u64,
+    pub n75: u64,
+    pub n90: u64,
 }
 
 impl NStats {
-    pub fn new(contigs: &[u32]) -> Self {
-        let mut nstats = Self {
-            sorted_contigs: sort_vec_desc(contigs),
-            csum_contigs: Vec::new(),
-            sum_contigs: contigs.iter().sum::<u32>(),
+    pub fn new(contigs: &LengthDist) -> Self {
+        Self {
+            contigs: contigs.counts.iter()
+                .rev()
+                .map(|(len, count)| (*len, *count))
+                .collect(),
+            sum_contigs: contigs.counts.iter()
+                .map(|(len, count)| len * count)
+                .sum(),
             n50_len: 0,
             n75_len: 0,
             n90_len: 0,
             n50: 0,
             n75: 0,
             n90: 0,
-        };
-
-        nstats.csum_contigs = cumsum(&nstats.sorted_contigs); 
-
-        nstats
+        }
     }
 
     pub fn get_n50(&mut self) {
         self.n50_len = self.n_len(0.5);
-        let idx = self.get_n_idx(self.n50_len);
-        self.n50 = self.sorted_contigs[idx];
+        self.n50 = self.get_n_contig(self.n50_len);
     }
 
     pub fn get_n75(&mut self) {
         self.n75_len = self.n_len(0.75);
-        let idx = self.get_n_idx(self.n75_len);
-        self.n75 = self.sorted_contigs[idx];
+        self.n75 = self.get_n_contig(self.n75_len);
     }
 
     pub fn get_n90(&mut self) {
         self.n90_len = self.n_len(0.9);
-        let idx = self.get_n_idx(self.n90_len);
-        self.n90 = self.sorted_contigs[idx];
+        self.n90 = self.get_n_contig(self.n90_len);
     }
 
-    fn get_n_idx(&mut self, n: u32) -> usize {
-        self.csum_contigs.iter()
-            .position(|i| *i >= n)
-            .unwrap()
+    // Length of the contig at which the cumulative sum 
+    // of the sorted contigs reaches n.
+    fn get_n_contig(&self, n: u64) -> u64 {
+        let mut csum = 0;
+        for (len, count) in self.contigs.iter() {
+            csum += len * count;
+            if csum >= n {
+                return *len;
+            }
+        }
+
+        0
     }
 
-    fn n_len(&mut self, i: f64) -> u32 {
+    fn n_len(&mut self, i: f64) -> u64 {
         let n = self.sum_contigs as f64 * i;   
 
-        n as u32
+        n as u64
     }
 
 }
@@ -141,19 +412,20 @@ impl NStats {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::sequence::GC_DEVIATION_WARN;
     use assert_approx_eq::assert_approx_eq;
 
     #[test]
     fn median_test() {
-        let odd: Vec<u32> = vec![1, 4, 3, 5, 6];
-        let even: Vec<u32> = vec![1, 4, 3, 5, 6, 6, 8, 10];
+        let odd: Vec<u64> = vec![1, 4, 3, 5, 6];
+        let even: Vec<u64> = vec![1, 4, 3, 5, 6, 6, 8, 10];
         assert_eq!(4.0, median(&odd));
         assert_eq!(5.5, median(&even));
     }
     
     #[test]
     fn var_test() {
-        let data: Vec<u32> = vec![1, 4, 3, 5, 6, 6, 8, 10];
+        let data: Vec<u64> = vec![1, 4, 3, 5, 6, 6, 8, 10];
         let mean = 5.375;
         
         let exp = 7.982143;
@@ -163,7 +435,7 @@ mod test {
 
     #[test]
     fn stdev_test() {
-        let data: Vec<u32> = vec![1, 4, 3, 5, 6, 6, 8, 10];
+        let data: Vec<u64> = vec![1, 4, 3, 5, 6, 6, 8, 10];
         let mean = 5.375;
 
         let exp = 2.825269;
@@ -172,24 +444,93 @@ mod test {
     }
 
     #[test]
-    fn csum_test() {
-        let a = vec![1, 2, 3];
-        let res = vec![1, 3, 6];
-        assert_eq!(res, cumsum(&a));
+    fn length_histogram_test() {
+        let mut dist = LengthDist::default();
+        [35, 36, 49, 70, 150, 1].iter().for_each(|len| dist.add(*len));
+
+        let hist = dist.histogram(LengthBins::Width(10));
+        assert_eq!(16, hist.len());
+        assert_eq!(LengthBin { start: 0, end: 9, count: 1 }, hist[0]);
+        assert_eq!(LengthBin { start: 30, end: 39, count: 2 }, hist[3]);
+        assert_eq!(0, hist[5].count);
+        assert_eq!(LengthBin { start: 150, end: 159, count: 1 }, hist[15]);
+
+        let hist = dist.histogram(LengthBins::Log2);
+        assert_eq!(8, hist.len());
+        assert_eq!(LengthBin { start: 1, end: 1, count: 1 }, hist[0]);
+        assert_eq!(LengthBin { start: 32, end: 63, count: 3 }, hist[5]);
+        assert_eq!(LengthBin { start: 128, end: 255, count: 1 }, hist[7]);
+
+        let long = LengthBins::Log2.bin(33);
+        assert_eq!((1 << 32, (1 << 33) - 1), (long.start, long.end));
+        let longest = LengthBins::Log2.bin(64);
+        assert_eq!((1 << 63, u64::MAX), (longest.start, longest.end));
+        assert!(LengthDist::default().histogram(LengthBins::Log2).is_empty());
+    }
+
+    #[test]
+    fn gc_dist_test() {
+        let mut normal = GcDist::default();
+        let mut other = GcDist::default();
+        [(40, 100), (50, 100), (50, 100), (60, 100)].iter()
+            .for_each(|(gc, len)| normal.add(*gc, *len));
+        other.add(1, 2);
+        other.add(5, 0);
+        normal.merge(&other);
+
+        let profile = normal.fit_normal();
+        let expected: f64 = profile.bins.iter().map(|bin| bin.theoretical).sum();
+
+        let observed: f64 = profile.bins.iter().map(|bin| bin.count).sum();
+
+        assert_eq!(101, profile.bins.len());
+        assert_approx_eq!(2.02, profile.bins[50].count, 1e-9);
+        assert_approx_eq!(1.02, profile.bins[50 - 10].count, 1e-9);
+        assert_approx_eq!(0.01, profile.bins[25].count, 1e-9);
+        assert_eq!(0.0, profile.bins[24].count);
+        assert_approx_eq!(5.0, observed, 1e-9);
+        assert_approx_eq!(5.0, expected, 0.1);
+
+        let mut bimodal = GcDist::default();
+        (0..50).for_each(|_| bimodal.add(30, 100));
+        (0..50).for_each(|_| bimodal.add(70, 100));
+
+        assert!(bimodal.fit_normal().deviation > profile.deviation);
+        assert!(GcDist::default().fit_normal().bins.is_empty());
+        assert_approx_eq!(0.0, erf(0.0), 1e-7);
+        assert_approx_eq!(0.8427008, erf(1.0), 1e-6);
+        assert_approx_eq!(-0.8427008, erf(-1.0), 1e-6);
     }
 
     #[test]
-    fn sorted_vec_desc_test() {
-        let a = vec![1, 2, 3];
-        let res = vec![3, 2, 1];
+    fn binomial_gc_dist_test() {
+        let mut state: u64 = 7;
+        let mut random_gc = |len: u64| {
+            (0..len)
+                .filter(|_| {
+                    state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                    state >> 63 == 1
+                })
+                .count() as u64
+        };
+
+        [50, 150, 151, 250].iter()
+            .for_each(|len| {
+                let mut dist = GcDist::default();
+                (0..20_000).for_each(|_| dist.add(random_gc(*len), *len));
+                let profile = dist.fit_normal();
 
-        assert_eq!(res, sort_vec_desc(&a));
+                assert!(profile.deviation < GC_DEVIATION_WARN);
+            });
     }
 
     #[test]
     fn n50_stats_test() {
         let contigs = vec![2,3,4,5,6,7,8,9,10];
-        let mut seq = NStats::new(&contigs);
+        let mut dist = LengthDist::default();
+        contigs.iter().for_each(|c| dist.add(*c));
+
+        let mut seq = NStats::new(&dist);
         seq.get_n50();
         seq.get_n90();
         seq.get_n75();
@@ -201,4 +542,79 @@ mod test {
         assert_eq!(48, seq.n90_len);
         assert_eq!(4, seq.n90);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn length_dist_test() {
+        let odd: Vec<u64> = vec![1, 4, 3, 5, 6];
+        let even: Vec<u64> = vec![1, 4, 3, 5, 6, 6, 8, 10];
+        let mut odd_dist = LengthDist::default();
+        let mut even_dist = LengthDist::default();
+        odd.iter().for_each(|v| odd_dist.add(*v));
+        even.iter().for_each(|v| even_dist.add(*v));
+
+        assert_eq!(median(&odd), odd_dist.median());
+        assert_eq!(median(&even), even_dist.median());
+        assert_eq!(1, even_dist.min());
+        assert_eq!(10, even_dist.max());
+        assert_eq!(4, even_dist.count_above(5));
+    }
+
+    #[test]
+    fn empty_length_dist_test() {
+        let dist = LengthDist::default();
+
+        assert_eq!(0.0, dist.median());
+        assert_eq!(0, dist.min());
+        assert_eq!(0, dist.max());
+    }
+
+    #[test]
+    fn online_stdev_test() {
+        let data: Vec<u64> = vec![1, 4, 3, 5, 6, 6, 8, 10];
+        let mean = 5.375;
+        let mut var = OnlineVariance::default();
+        data.iter().for_each(|v| var.add(*v as f64));
+
+        assert_approx_eq!(stdev(&data, &mean), var.stdev(), 1e-12);
+    }
+
+    #[test]
+    fn merge_stats_test() {
+        let data = vec![1, 4, 4, 5, 9, 10, 12];
+        let mean = data.iter().sum::<u64>() as f64 / data.len() as f64;
+        let (left, right) = data.split_at(3);
+
+        let mut dist = LengthDist::default();
+        let mut var = OnlineVariance::default();
+        left.iter().for_each(|v| { dist.add(*v); var.add(*v as f64); });
+
+        let mut dist_right = LengthDist::default();
+        let mut var_right = OnlineVariance::default();
+        right.iter().for_each(|v| { dist_right.add(*v); var_right.add(*v as f64); });
+
+        dist.merge(&dist_right);
+        var.merge(&var_right);
+
+        assert_eq!(5.0, dist.median());
+        assert_eq!(12, dist.max());
+        assert_approx_eq!(stdev(&data, &mean), var.stdev(), 1e-12);
+    }
+
+    #[test]
+    fn n50_large_assembly_test() {
+        // 3 x 2 Gbp contigs and one short contig. 
+        // The cumulative sum is above the u32 limit.
+        let mut dist = LengthDist::default();
+        (0..3).for_each(|_| dist.add(2_000_000_000));
+        dist.add(1_000);
+
+        let mut seq = NStats::new(&dist);
+        seq.get_n50();
+        seq.get_n90();
+
+        assert_eq!(6_000_001_000, seq.sum_contigs);
+        assert_eq!(3_000_000_500, seq.n50_len);
+        assert_eq!(2_000_000_000, seq.n50);
+        assert_eq!(2_000_000_000, seq.n90);
+    }
+}
@@ -0,0 +1,139 @@
+//! Heru Handika
+//! Homopolymer tails at the 3' end of reads. Two-colour
+//! chemistry (NovaSeq, NextSeq) reads no signal as G,
+//! so reads running past the insert end in poly-G.
+
+// Tail bases: G, A, and T.
+const TAIL_BASES: [u8; 3] = [b'G', b'A', b'T'];
+// Tails allow one mismatch per this many bases, as in fastp.
+const BASES_PER_MISMATCH: usize = 8;
+
+/// A poly-G, poly-A, or poly-T tail.
+#[derive(Debug, PartialEq)]
+pub struct PolyTail {
+    /// Index of the base in G, A, and T.
+    pub base: usize,
+    pub len: usize,
+    /// G and C bases in the tail.
+    pub gc: usize,
+}
+
+/// Tail of the read if its last min_len or more bases
+/// are the same G, A, or T. The tail ends at the last base 
+/// and allows one mismatch per eight bases.
+pub fn find_tail(seq: &[u8], min_len: usize) -> Option<PolyTail> {
+    let last = seq.last()?.to_ascii_uppercase();
+    let base = TAIL_BASES.iter().position(|tail| *tail == last)?;
+    let mut mismatches = 0;
+    let mut len = 0;
+
+    for (idx, b) in seq.iter().rev().enumerate() {
+        if b.to_ascii_uppercase() == last {
+            len = idx + 1;
+        } else {
+            mismatches += 1;
+            if mismatches > (idx + 1) / BASES_PER_MISMATCH {
+                break;
+            }
+        }
+    }
+
+    let gc = seq[seq.len() - len..].iter()
+        .filter(|b| matches!(b.to_ascii_uppercase(), b'G' | b'C'))
+        .count();
+
+    (len >= min_len).then_some(PolyTail { base, len, gc })
+}
+
+/// Reads and bases in each kind of tail.
+#[derive(Default)]
+pub struct TailCounts {
+    reads: [u64; 3],
+    bases: [u64; 3],
+    gc: u64,
+}
+
+impl TailCounts {
+    pub fn add(&mut self, tail: Option<&PolyTail>) {
+        if let Some(tail) = tail {
+            self.reads[tail.base] += 1;
+            self.bases[tail.base] += tail.len as u64;
+            self.gc += tail.gc as u64;
+        }
+    }
+
+    pub fn merge(&mut self, other: &TailCounts) {
+        (0..TAIL_BASES.len()).for_each(|idx| {
+            self.reads[idx] += other.reads[idx];
+            self.bases[idx] += other.bases[idx];
+        });
+        self.gc += other.gc;
+    }
+
+    pub fn poly_g(&self) -> u64 {
+        self.reads[0]
+    }
+
+    pub fn poly_a(&self) -> u64 {
+        self.reads[1]
+    }
+
+    pub fn poly_t(&self) -> u64 {
+        self.reads[2]
+    }
+
+    /// Mean length of all tails.
+    pub fn mean_len(&self) -> f64 {
+        let reads: u64 = self.reads.iter().sum();
+        if reads == 0 {
+            return 0.0;
+        }
+
+        self.bases.iter().sum::<u64>() as f64 / reads as f64
+    }
+
+    /// GC-content without the tail bases.
+    /// Zero if all bases are in tails.
+    pub fn gc_content_without(&self, total_gc: u64, total_bp: u64) -> f64 {
+        let tail_bases: u64 = self.bases.iter().sum();
+        if total_bp <= tail_bases {
+            return 0.0;
+        }
+
+        (total_gc - self.gc) as f64 / (total_bp - tail_bases) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_tail_test() {
+        assert_eq!(Some(PolyTail { base: 0, len: 5, gc: 5 }), find_tail(b"ACGTAggGGG", 5));
+        assert_eq!(Some(PolyTail { base: 2, len: 4, gc: 0 }), find_tail(b"TTTT", 3));
+        assert_eq!(Some(PolyTail { base: 0, len: 16, gc: 15 }), 
+            find_tail(b"CTCTCTGGGGGGGAGGGGGGGG", 10));
+        assert_eq!(None, find_tail(b"GGGAGGGGGG", 8));
+        assert_eq!(None, find_tail(b"ACGTAAAA", 5));
+        assert_eq!(None, find_tail(b"ACGTCCCCCC", 5));
+        assert_eq!(None, find_tail(b"", 5));
+    }
+
+    #[test]
+    fn tail_counts_test() {
+        let mut counts = TailCounts::default();
+        let mut other = TailCounts::default();
+        counts.add(find_tail(b"CCCCGGGGGGGGGG", 10).as_ref());
+        counts.add(find_tail(b"ACGTACGTAC", 10).as_ref());
+        other.add(find_tail(b"CCCCAAAAAAAAAAAA", 10).as_ref());
+        counts.merge(&other);
+
+        assert_eq!(1, counts.poly_g());
+        assert_eq!(1, counts.poly_a());
+        assert_eq!(0, counts.poly_t());
+        assert_eq!(11.0, counts.mean_len());
+        assert_eq!(0.5, counts.gc_content_without(21, 44));
+        assert_eq!(0.0, counts.gc_content_without(11, 22));
+    }
+}
@@ -0,0 +1,113 @@
+//! Heru Handika
+//! Read counts and quality per flowcell lane and tile,
+//! from Casava 1.8 read IDs. Bubbles and edge effects
+//! show up as tiles with a lower mean q-score.
+
+use std::collections::BTreeMap;
+
+use crate::header::IlluminaId;
+
+#[derive(Clone, Copy, Default)]
+struct TileCounts {
+    reads: u64,
+    sum_qscores: f64,
+}
+
+impl TileCounts {
+    fn merge(&mut self, other: &TileCounts) {
+        self.reads += other.reads;
+        self.sum_qscores += other.sum_qscores;
+    }
+}
+
+/// Reads and q-scores of each tile, keyed by lane and tile.
+#[derive(Default)]
+pub struct TileQuality {
+    tiles: BTreeMap<(u32, u32), TileCounts>,
+}
+
+impl TileQuality {
+    pub fn add(&mut self, id: &IlluminaId, mean_q: f64) {
+        let counts = self.tiles.entry((id.lane, id.tile)).or_default();
+        counts.reads += 1;
+        counts.sum_qscores += mean_q;
+    }
+
+    pub fn merge(&mut self, other: &TileQuality) {
+        other.tiles.iter()
+            .for_each(|(key, counts)| self.tiles.entry(*key).or_default().merge(counts));
+    }
+
+    /// Per-lane totals, ordered by lane.
+    pub fn lanes(&self) -> Vec<LaneSummary> {
+        let mut lanes: BTreeMap<u32, TileCounts> = BTreeMap::new();
+        self.tiles.iter()
+            .for_each(|((lane, _), counts)| lanes.entry(*lane).or_default().merge(counts));
+
+        lanes.iter()
+            .map(|(lane, counts)| LaneSummary {
+                lane: *lane,
+                reads: counts.reads,
+                mean_q: counts.sum_qscores / counts.reads as f64,
+            })
+            .collect()
+    }
+
+    /// Per-tile totals, ordered by lane and tile.
+    pub fn tiles(&self) -> Vec<TileSummary> {
+        self.tiles.iter()
+            .map(|((lane, tile), counts)| TileSummary {
+                lane: *lane,
+                tile: *tile,
+                reads: counts.reads,
+                mean_q: counts.sum_qscores / counts.reads as f64,
+            })
+            .collect()
+    }
+}
+
+pub struct LaneSummary {
+    pub lane: u32,
+    pub reads: u64,
+    /// Mean of the per-read mean q-scores.
+    pub mean_q: f64,
+}
+
+pub struct TileSummary {
+    pub lane: u32,
+    pub tile: u32,
+    pub reads: u64,
+    /// Mean of the per-read mean q-scores.
+    pub mean_q: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header;
+
+    #[test]
+    fn tile_quality_test() {
+        let mut tiles = TileQuality::default();
+        let mut other = TileQuality::default();
+        let tile_1 = header::illumina_id(b"@A00:1:FC:1:1101:10:20 1:N:0:ACGT").unwrap();
+        let tile_2 = header::illumina_id(b"@A00:1:FC:1:1102:10:20 1:N:0:ACGT").unwrap();
+        let lane_2 = header::illumina_id(b"@A00:1:FC:2:1101:10:20 1:N:0:ACGT").unwrap();
+        tiles.add(&tile_2, 30.0);
+        tiles.add(&tile_1, 36.0);
+        other.add(&tile_1, 34.0);
+        other.add(&lane_2, 20.0);
+        tiles.merge(&other);
+
+        let lanes = tiles.lanes();
+        let res = tiles.tiles();
+
+        assert_eq!(2, lanes.len());
+        assert_eq!(3, lanes[0].reads);
+        assert_eq!(100.0 / 3.0, lanes[0].mean_q);
+        assert_eq!(3, res.len());
+        assert_eq!((1, 1101, 2), (res[0].lane, res[0].tile, res[0].reads));
+        assert_eq!(35.0, res[0].mean_q);
+        assert_eq!((2, 1101), (res[2].lane, res[2].tile));
+    }
+}